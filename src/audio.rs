@@ -0,0 +1,268 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use alsa::{
+    pcm::{Access, Format, HwParams, PCM},
+    Direction, ValueOr,
+};
+use anyhow::{anyhow, Result};
+use hound::{SampleFormat, WavReader};
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+
+const SAMPLE_RATE: u32 = 44100;
+
+// Enough for the largest FFT the visualizers use, with some slack
+const CAPACITY: usize = 8192;
+
+// WAV files are fed to the buffer in chunks of this length, like a capture device would
+const WAV_CHUNK: Duration = Duration::from_millis(20);
+
+pub enum AudioSource {
+    /// ALSA capture device name, like `default` or `hw:1,0`
+    Alsa(String),
+
+    /// WAV file that is played in a loop, so visualizers can be tried without a microphone
+    Wav(PathBuf),
+}
+
+struct AudioState {
+    samples: VecDeque<f32>,
+    sample_rate: u32,
+}
+
+/// The most recent mono samples, from -1.0 to 1.0
+///
+/// Filled by the audio thread and read by the visualizers on the matrix thread.
+#[derive(Clone)]
+pub struct AudioBuffer(Arc<Mutex<AudioState>>);
+
+impl AudioBuffer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self(Arc::new(Mutex::new(AudioState {
+            samples: VecDeque::with_capacity(CAPACITY),
+            sample_rate,
+        })))
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.0.lock().expect("audio lock poisoned").sample_rate
+    }
+
+    fn set_sample_rate(&self, sample_rate: u32) {
+        let mut state = self.0.lock().expect("audio lock poisoned");
+
+        state.sample_rate = sample_rate;
+        state.samples.clear();
+    }
+
+    pub fn push(&self, samples: impl IntoIterator<Item = f32>) {
+        let mut state = self.0.lock().expect("audio lock poisoned");
+
+        state.samples.extend(samples);
+
+        let excess = state.samples.len().saturating_sub(CAPACITY);
+        state.samples.drain(..excess);
+    }
+
+    /// The last `count` samples, padded with silence at the start if there aren't enough yet
+    pub fn latest(&self, count: usize) -> Vec<f32> {
+        let state = self.0.lock().expect("audio lock poisoned");
+
+        let available = state.samples.len().min(count);
+        let mut result = vec![0.0; count - available];
+
+        result.extend(state.samples.range(state.samples.len() - available..));
+        result
+    }
+}
+
+/// Turns blocks of samples into the magnitudes of their frequencies
+pub struct Analyzer {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+}
+
+impl Analyzer {
+    /// Analyzes blocks of `size` samples, which gives `size / 2` frequency bins
+    pub fn new(size: usize) -> Self {
+        // Hann window, so the edges of the sample block don't show up as noise in every bin
+        let window = (0..size)
+            .map(|i| {
+                let t = i as f32 / (size - 1) as f32;
+
+                0.5 - 0.5 * (std::f32::consts::TAU * t).cos()
+            })
+            .collect();
+
+        Self {
+            fft: FftPlanner::new().plan_fft_forward(size),
+            window,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Width of a single frequency bin in Hz
+    pub fn bin_width(&self, sample_rate: u32) -> f32 {
+        sample_rate as f32 / self.size() as f32
+    }
+
+    /// Magnitudes of the positive frequency bins, a full scale sine ends up at about 1.0
+    pub fn magnitudes(&self, samples: &[f32]) -> Vec<f32> {
+        let mut buffer = samples
+            .iter()
+            .zip(&self.window)
+            .map(|(sample, window)| Complex::new(sample * window, 0.0))
+            .collect::<Vec<_>>();
+
+        buffer.resize(self.size(), Complex::new(0.0, 0.0));
+        self.fft.process(&mut buffer);
+
+        // The window halves the amplitude, and half of it ends up in the negative frequencies
+        let full_scale = self.size() as f32 / 4.0;
+
+        buffer[..self.size() / 2]
+            .iter()
+            .map(|bin| bin.norm() / full_scale)
+            .collect()
+    }
+}
+
+/// Root mean square of `samples`, a full scale sine is about 0.7
+pub fn rms(samples: &[f32]) -> f32 {
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32).sqrt()
+}
+
+pub fn to_db(magnitude: f32) -> f32 {
+    20.0 * magnitude.max(1e-6).log10()
+}
+
+/// Starts reading audio from `source` on a separate thread
+///
+/// Errors while reading are printed, and stop the audio thread. The buffer then stays silent.
+pub fn start(source: AudioSource) -> AudioBuffer {
+    let buffer = AudioBuffer::new(SAMPLE_RATE);
+
+    std::thread::spawn({
+        let buffer = buffer.clone();
+
+        move || {
+            let result = match &source {
+                AudioSource::Alsa(device) => capture_alsa(device, &buffer),
+                AudioSource::Wav(path) => play_wav(path, &buffer),
+            };
+
+            if let Err(why) = result {
+                log::error!("Audio input stopped: {why}");
+            }
+        }
+    });
+
+    buffer
+}
+
+fn capture_alsa(device: &str, buffer: &AudioBuffer) -> Result<()> {
+    let pcm = PCM::new(device, Direction::Capture, false)?;
+
+    {
+        let params = HwParams::any(&pcm)?;
+
+        params.set_channels(1)?;
+        params.set_rate(SAMPLE_RATE, ValueOr::Nearest)?;
+        params.set_format(Format::s16())?;
+        params.set_access(Access::RWInterleaved)?;
+
+        pcm.hw_params(&params)?;
+    }
+
+    buffer.set_sample_rate(pcm.hw_params_current()?.get_rate()?);
+
+    let io = pcm.io_i16()?;
+    let mut chunk = [0i16; 512];
+
+    pcm.start()?;
+
+    loop {
+        match io.readi(&mut chunk) {
+            Ok(count) => buffer.push(chunk[..count].iter().map(|s| *s as f32 / i16::MAX as f32)),
+
+            // Overruns happen when the thread doesn't get scheduled for a while
+            Err(why) => pcm.try_recover(why, true)?,
+        }
+    }
+}
+
+fn play_wav(path: &Path, buffer: &AudioBuffer) -> Result<()> {
+    loop {
+        let mut reader = WavReader::open(path)?;
+        let spec = reader.spec();
+
+        if spec.channels == 0 {
+            return Err(anyhow!("WAV file has no channels"));
+        }
+
+        if spec.sample_rate == 0 {
+            return Err(anyhow!("WAV file has no sample rate"));
+        }
+
+        if spec.sample_format == SampleFormat::Int && !(1..=32).contains(&spec.bits_per_sample) {
+            return Err(anyhow!(
+                "WAV file has {} bits per sample",
+                spec.bits_per_sample
+            ));
+        }
+
+        // Replaying an empty file would spin without ever sleeping
+        if reader.duration() == 0 {
+            return Err(anyhow!("WAV file has no samples"));
+        }
+
+        buffer.set_sample_rate(spec.sample_rate);
+
+        let samples: Box<dyn Iterator<Item = f32>> = match spec.sample_format {
+            SampleFormat::Float => Box::new(reader.samples::<f32>().map_while(Result::ok)),
+            SampleFormat::Int => {
+                let max = (1i64 << (spec.bits_per_sample - 1)) as f32;
+
+                Box::new(
+                    reader
+                        .samples::<i32>()
+                        .map_while(Result::ok)
+                        .map(move |s| s as f32 / max),
+                )
+            }
+        };
+
+        let channels = spec.channels as usize;
+        // At least a sample per channel, or very low rates would never push anything
+        let frames = (spec.sample_rate as f32 * WAV_CHUNK.as_secs_f32()) as usize;
+        let chunk_len = frames.max(1) * channels;
+
+        let mut samples = samples.peekable();
+        let mut next_chunk = Instant::now();
+
+        if samples.peek().is_none() {
+            return Err(anyhow!("WAV file has no readable samples"));
+        }
+
+        while samples.peek().is_some() {
+            // Mix all channels down to mono
+            let chunk = samples.by_ref().take(chunk_len).collect::<Vec<_>>();
+            buffer.push(
+                chunk
+                    .chunks(channels)
+                    .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32),
+            );
+
+            // Feed the samples in real time
+            next_chunk += WAV_CHUNK;
+            std::thread::sleep(next_chunk.saturating_duration_since(Instant::now()));
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use std::time::{Duration, Instant};
+
+use streamdeck_hid_rs::{ButtonEvent, ButtonState};
+
+/// How long, and how close together, presses have to be to count as a gesture
+#[derive(Clone, Copy, Debug)]
+pub struct GestureConfig {
+    /// Holding a key at least this long is a long-press
+    pub long_press: Duration,
+
+    /// Tapping a key again within this time is a double-tap
+    ///
+    /// Presses only come through after this time has passed, `None` turns double-taps off so
+    /// they come through right away.
+    pub double_tap: Option<Duration>,
+
+    /// Two keys that go down within this time of each other are a chord
+    pub chord: Duration,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            long_press: Duration::from_millis(600),
+            double_tap: None,
+            chord: Duration::from_millis(100),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GestureKind {
+    /// The key went down, for showing it pressed. Comes before every other gesture of the key.
+    Down,
+
+    /// Short press and release
+    Press,
+
+    /// Held for [`GestureConfig::long_press`], comes in while the key is still down
+    LongPress,
+    DoubleTap,
+
+    /// Pressed together with the other key, which went down first
+    Chord(u8),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Gesture {
+    pub button_id: u8,
+    pub kind: GestureKind,
+
+    /// When the gesture was made, which is when the key went down for a chord and when the
+    /// threshold was reached for a long-press
+    pub at: Instant,
+}
+
+struct Held {
+    button_id: u8,
+    since: Instant,
+
+    /// Whether this press already turned into a long-press or a chord
+    used: bool,
+}
+
+/// Turns raw button events into [`Gesture`]s
+pub struct GestureDetector {
+    config: GestureConfig,
+    held: Vec<Held>,
+
+    /// A press that can still become a double-tap, and when it was released
+    tap: Option<(u8, Instant)>,
+
+    /// The key that is down for the second time of a double-tap
+    second_tap: Option<u8>,
+}
+
+impl GestureDetector {
+    pub fn new(config: GestureConfig) -> Self {
+        Self {
+            config,
+            held: vec![],
+            tap: None,
+            second_tap: None,
+        }
+    }
+
+    pub fn set_config(&mut self, config: GestureConfig) {
+        self.config = config;
+    }
+
+    /// Forgets the keys that are held and the taps that are waited on
+    pub fn reset(&mut self) {
+        *self = Self::new(self.config);
+    }
+
+    /// The first moment a gesture can be made without new button events
+    pub fn deadline(&self) -> Option<Instant> {
+        let long_press = self
+            .held
+            .iter()
+            .filter(|held| !held.used)
+            .map(|held| held.since + self.config.long_press);
+
+        let tap = self
+            .tap
+            .zip(self.config.double_tap)
+            .map(|((_, released), window)| released + window);
+
+        long_press.chain(tap).min()
+    }
+
+    /// Handles a button event that happened at `at`, returns the gestures it completed
+    pub fn event(&mut self, event: ButtonEvent, at: Instant) -> Vec<Gesture> {
+        let mut gestures = self.tick(at);
+        let button_id = event.button_id as u8;
+
+        let gesture = |kind| Gesture {
+            button_id,
+            kind,
+            at,
+        };
+
+        match event.state {
+            ButtonState::Down => {
+                // Another key ends the wait for a double-tap
+                match self.tap.take() {
+                    Some((tapped, _)) if tapped == button_id => self.second_tap = Some(tapped),
+                    Some((tapped, released)) => gestures.push(Gesture {
+                        button_id: tapped,
+                        kind: GestureKind::Press,
+                        at: released,
+                    }),
+                    None => {}
+                }
+
+                gestures.push(gesture(GestureKind::Down));
+
+                let chord = self.held.iter_mut().find(|held| {
+                    !held.used
+                        && held.button_id != button_id
+                        && at - held.since <= self.config.chord
+                });
+
+                let used = if let Some(held) = chord {
+                    held.used = true;
+
+                    gestures.push(Gesture {
+                        button_id: held.button_id,
+                        kind: GestureKind::Chord(button_id),
+                        at,
+                    });
+
+                    true
+                } else {
+                    false
+                };
+
+                self.held.retain(|held| held.button_id != button_id);
+                self.held.push(Held {
+                    button_id,
+                    since: at,
+                    used,
+                });
+            }
+
+            ButtonState::Up => {
+                let Some(index) = self
+                    .held
+                    .iter()
+                    .position(|held| held.button_id == button_id)
+                else {
+                    return gestures;
+                };
+
+                let held = self.held.remove(index);
+                let second_tap = self.second_tap.take() == Some(button_id);
+
+                if held.used {
+                    return gestures;
+                }
+
+                if second_tap {
+                    gestures.push(gesture(GestureKind::DoubleTap));
+                } else if self.config.double_tap.is_some() {
+                    self.tap = Some((button_id, at));
+                } else {
+                    gestures.push(gesture(GestureKind::Press));
+                }
+            }
+        }
+
+        gestures
+    }
+
+    /// Returns the gestures that were completed by time passing until `now`
+    pub fn tick(&mut self, now: Instant) -> Vec<Gesture> {
+        let mut gestures = vec![];
+
+        for held in self.held.iter_mut().filter(|held| !held.used) {
+            let at = held.since + self.config.long_press;
+
+            if now >= at {
+                held.used = true;
+
+                gestures.push(Gesture {
+                    button_id: held.button_id,
+                    kind: GestureKind::LongPress,
+                    at,
+                });
+            }
+        }
+
+        if let (Some((tapped, released)), Some(window)) = (self.tap, self.config.double_tap) {
+            if now >= released + window {
+                self.tap = None;
+
+                gestures.push(Gesture {
+                    button_id: tapped,
+                    kind: GestureKind::Press,
+                    at: released,
+                });
+            }
+        }
+
+        gestures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gestures_from_button_events() {
+        let config = GestureConfig {
+            long_press: Duration::from_millis(500),
+            double_tap: Some(Duration::from_millis(200)),
+            chord: Duration::from_millis(50),
+        };
+
+        let mut detector = GestureDetector::new(config);
+        let start = Instant::now();
+
+        let kinds = |detector: &mut GestureDetector, button_id: u8, state, ms: u64| {
+            let event = ButtonEvent {
+                button_id: button_id as u32,
+                state,
+            };
+
+            detector
+                .event(event, start + Duration::from_millis(ms))
+                .into_iter()
+                .map(|gesture| (gesture.button_id, gesture.kind))
+                .collect::<Vec<_>>()
+        };
+
+        use ButtonState::{Down, Up};
+        use GestureKind::{Chord, DoubleTap, LongPress, Press};
+
+        // A press waits for a second tap, until another key goes down
+        assert_eq!(kinds(&mut detector, 1, Down, 0), [(1, GestureKind::Down)]);
+        assert_eq!(kinds(&mut detector, 1, Up, 100), []);
+        assert_eq!(
+            kinds(&mut detector, 2, Down, 150),
+            [(1, Press), (2, GestureKind::Down)]
+        );
+        assert_eq!(kinds(&mut detector, 2, Up, 200), []);
+
+        // Or until the window is over
+        assert_eq!(
+            kinds(&mut detector, 1, Down, 1000),
+            [(2, Press), (1, GestureKind::Down)]
+        );
+        assert_eq!(kinds(&mut detector, 1, Up, 1100), []);
+        assert_eq!(
+            kinds(&mut detector, 1, Down, 1200),
+            [(1, GestureKind::Down)]
+        );
+        assert_eq!(kinds(&mut detector, 1, Up, 1300), [(1, DoubleTap)]);
+
+        // Held keys are long-pressed when the threshold passes, and don't press when let go
+        assert_eq!(
+            kinds(&mut detector, 3, Down, 2000),
+            [(3, GestureKind::Down)]
+        );
+        assert_eq!(
+            detector.deadline(),
+            Some(start + Duration::from_millis(2500))
+        );
+        assert_eq!(kinds(&mut detector, 3, Up, 2600), [(3, LongPress)]);
+
+        // Two keys at once are a chord of the first key, and neither is pressed
+        assert_eq!(
+            kinds(&mut detector, 4, Down, 3000),
+            [(4, GestureKind::Down)]
+        );
+        assert_eq!(
+            kinds(&mut detector, 5, Down, 3020),
+            [(5, GestureKind::Down), (4, Chord(5))]
+        );
+        assert_eq!(kinds(&mut detector, 4, Up, 3100), []);
+        assert_eq!(kinds(&mut detector, 5, Up, 3110), []);
+        assert_eq!(detector.deadline(), None);
+    }
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use hidapi::HidApi;
+use streamdeck_hid_rs::{ButtonEvent, StreamDeckDevice};
+
+use crate::image::RgbImage;
+
+use super::{DeckDevice, DeckLayout};
+
+/// Opens the first StreamDeck that is plugged in
+pub fn open() -> Result<Arc<dyn DeckDevice>> {
+    let hidapi = HidApi::new()?;
+    let device = StreamDeckDevice::open_first_device(&hidapi)
+        .map_err(|_| anyhow!("Failed to open Stream Deck device"))?;
+
+    Ok(Arc::new(device))
+}
+
+impl DeckDevice for StreamDeckDevice<HidApi> {
+    fn layout(&self) -> DeckLayout {
+        DeckLayout::new(
+            self.device_type.total_num_buttons(),
+            self.device_type.button_image_size(),
+        )
+    }
+
+    fn set_button_image(&self, index: u8, image: &RgbImage) -> Result<()> {
+        StreamDeckDevice::set_button_image(self, index, image)
+            .map_err(|why| anyhow!("Failed to set button image: {why:?}"))
+    }
+
+    fn set_brightness(&self, percent: u8) -> Result<()> {
+        StreamDeckDevice::set_brightness(self, percent)
+            .map_err(|why| anyhow!("Failed to set brightness: {why:?}"))
+    }
+
+    fn read_button_events(&self, callback: &dyn Fn(ButtonEvent)) -> Result<()> {
+        self.on_button_events(callback)
+            .map_err(|why| anyhow!("HID communication failure: {why:?}"))
+    }
+}
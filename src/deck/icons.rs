@@ -0,0 +1,70 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+
+use crate::image::{self, ImageSourceType, RgbImage};
+
+/// The icons that were shown on a [`super::Deck`], so every icon is only decoded and scaled
+/// once instead of on every redraw
+///
+/// Icons are the JPEG images that are built into the app. They are found again by their bytes,
+/// the same icon can be at more than one address.
+pub struct IconCache {
+    key_size: (u32, u32),
+    icons: Mutex<HashMap<&'static [u8], Arc<RgbImage>>>,
+}
+
+impl IconCache {
+    pub fn new(key_size: (u32, u32)) -> Self {
+        Self {
+            key_size,
+            icons: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The icon `jpeg`, decoded and scaled to the key size
+    pub fn get(&self, jpeg: &'static [u8]) -> Result<Arc<RgbImage>> {
+        let mut icons = self.icons.lock().expect("icon cache lock poisoned");
+
+        if let Some(icon) = icons.get(jpeg) {
+            return Ok(icon.clone());
+        }
+
+        let decoded = ImageSourceType::Jpeg(jpeg).decode()?;
+        let icon = Arc::new(fit(decoded, self.key_size));
+        icons.insert(jpeg, icon.clone());
+
+        Ok(icon)
+    }
+}
+
+/// Scales `image` to the key size, when it doesn't have it already
+pub fn fit(image: RgbImage, (width, height): (u32, u32)) -> RgbImage {
+    if image.dimensions() == (width, height) {
+        image
+    } else {
+        image::resize(&image, width, height, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMG_CAMERA: &[u8] = include_bytes!("../../images/camera.jpg");
+
+    #[test]
+    fn icons_are_decoded_once() {
+        let icons = IconCache::new((80, 80));
+
+        let camera = icons.get(IMG_CAMERA).unwrap();
+        assert_eq!(camera.dimensions(), (80, 80));
+
+        // The same icon at another address is found by its bytes
+        let copy: &'static [u8] = IMG_CAMERA.to_vec().leak();
+        assert!(Arc::ptr_eq(&camera, &icons.get(copy).unwrap()));
+    }
+}
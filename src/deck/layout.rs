@@ -0,0 +1,228 @@
+use crate::image::{self, RgbImage};
+
+/// Columns and rows of the 15 key StreamDeck, that all menus are designed for
+pub const MENU_COLUMNS: u8 = 5;
+pub const MENU_ROWS: u8 = 3;
+
+/// The grid of keys on a StreamDeck model
+///
+/// Menus address their keys on a 5x3 grid. On larger decks that grid sits in the middle, on
+/// smaller decks the outer columns and rows stay at the edges and the keys in the middle that
+/// don't fit are left out. Pages that have buttons on those keys place them with a [`KeyMap`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DeckLayout {
+    pub columns: u8,
+    pub rows: u8,
+
+    /// Width and height of the image on a single key
+    pub key_size: (u32, u32),
+}
+
+impl DeckLayout {
+    /// The layout of the model with `button_count` keys
+    pub fn new(button_count: usize, key_size: (u32, u32)) -> Self {
+        let (columns, rows) = match button_count {
+            // Mini
+            6 => (3, 2),
+            // Plus and Neo
+            8 => (4, 2),
+            // Original and MK.2
+            15 => (5, 3),
+            // XL
+            32 => (8, 4),
+            count => (
+                count.min(MENU_COLUMNS as usize) as u8,
+                count.div_ceil(MENU_COLUMNS as usize) as u8,
+            ),
+        };
+
+        Self {
+            columns,
+            rows,
+            key_size,
+        }
+    }
+
+    pub fn button_count(&self) -> usize {
+        self.columns as usize * self.rows as usize
+    }
+
+    /// Size of all keys together, for images that are spread over the whole deck
+    pub fn screen_size(&self) -> (u32, u32) {
+        (
+            self.key_size.0 * self.columns as u32,
+            self.key_size.1 * self.rows as u32,
+        )
+    }
+
+    /// The key on the device for key `index` of a menu, if the deck has room for it
+    pub fn device_key(&self, index: u8) -> Option<u8> {
+        if index >= MENU_COLUMNS * MENU_ROWS {
+            return None;
+        }
+
+        let column = place(index % MENU_COLUMNS, MENU_COLUMNS, self.columns)?;
+        let row = place(index / MENU_COLUMNS, MENU_ROWS, self.rows)?;
+
+        Some(row * self.columns + column)
+    }
+
+    /// The menu key that is shown on key `device_key` of the device
+    pub fn menu_key(&self, device_key: u8) -> Option<u8> {
+        (0..MENU_COLUMNS * MENU_ROWS).find(|index| self.device_key(*index) == Some(device_key))
+    }
+
+    /// Scales `image` to cover the whole deck, and cuts it into an image for every key
+    ///
+    /// Images with a different aspect ratio than the deck are cropped in the middle.
+    pub fn split(&self, image: &RgbImage) -> Vec<RgbImage> {
+        let (width, height) = self.screen_size();
+
+        let scale = f32::max(
+            width as f32 / image.width() as f32,
+            height as f32 / image.height() as f32,
+        );
+
+        // Rounded, but never smaller than the deck
+        let scaled = if scale == 1.0 {
+            image.clone()
+        } else {
+            image::resize(
+                image,
+                ((image.width() as f32 * scale).round() as u32).max(width),
+                ((image.height() as f32 * scale).round() as u32).max(height),
+                true,
+            )
+        };
+
+        let x = (scaled.width() - width) / 2;
+        let y = (scaled.height() - height) / 2;
+
+        (0..self.button_count() as u32)
+            .map(|key| {
+                let column = key % self.columns as u32;
+                let row = key / self.columns as u32;
+
+                image::imageops::crop_imm(
+                    &scaled,
+                    x + column * self.key_size.0,
+                    y + row * self.key_size.1,
+                    self.key_size.0,
+                    self.key_size.1,
+                )
+                .to_image()
+            })
+            .collect()
+    }
+}
+
+/// Which menu key is shown on which key of the device
+///
+/// Menus are placed on the device with [`Self::grid`], unless a page doesn't fit on it that way
+/// and places its buttons itself.
+#[derive(Clone, PartialEq, Debug)]
+pub struct KeyMap {
+    /// The menu key on every key of the device
+    keys: Vec<Option<u8>>,
+}
+
+impl KeyMap {
+    /// The 5x3 menu grid on `layout`, see [`DeckLayout::device_key`]
+    pub fn grid(layout: DeckLayout) -> Self {
+        Self {
+            keys: (0..layout.button_count() as u8)
+                .map(|key| layout.menu_key(key))
+                .collect(),
+        }
+    }
+
+    /// Shows menu key `keys[n]` on key `n` of `layout`, the keys after the last one stay empty
+    pub fn new(layout: DeckLayout, mut keys: Vec<Option<u8>>) -> Self {
+        keys.resize(layout.button_count(), None);
+
+        Self { keys }
+    }
+
+    /// The key on the device for menu key `index`, if it is on the deck
+    pub fn device_key(&self, index: u8) -> Option<u8> {
+        self.keys
+            .iter()
+            .position(|key| *key == Some(index))
+            .map(|key| key as u8)
+    }
+
+    /// The menu key that is shown on key `device_key` of the device
+    pub fn menu_key(&self, device_key: u8) -> Option<u8> {
+        self.keys.get(device_key as usize).copied().flatten()
+    }
+}
+
+/// Where column or row `index` of a menu with `menu_size` of them ends up on a device with
+/// `device_size` of them
+fn place(index: u8, menu_size: u8, device_size: u8) -> Option<u8> {
+    if device_size >= menu_size {
+        return Some(index + (device_size - menu_size) / 2);
+    }
+
+    if index == 0 {
+        return Some(0);
+    }
+
+    if index == menu_size - 1 {
+        return (device_size > 1).then(|| device_size - 1);
+    }
+
+    // The middle of the menu, centered in the middle of the device
+    let skipped = (menu_size - device_size) / 2;
+
+    (index - 1)
+        .checked_sub(skipped)
+        .map(|middle| middle + 1)
+        .filter(|key| *key + 1 < device_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menu_keys_on_small_decks() {
+        let mini = DeckLayout::new(6, (80, 80));
+
+        // Corners stay in the corners
+        assert_eq!(mini.device_key(0), Some(0));
+        assert_eq!(mini.device_key(4), Some(2));
+        assert_eq!(mini.device_key(10), Some(3));
+        assert_eq!(mini.device_key(14), Some(5));
+
+        // The middle column fits, the middle row and the keys next to it don't
+        assert_eq!(mini.device_key(2), Some(1));
+        assert_eq!(mini.device_key(1), None);
+        assert_eq!(mini.device_key(7), None);
+        assert_eq!(mini.menu_key(4), Some(12));
+
+        let plus = DeckLayout::new(8, (120, 120));
+
+        assert_eq!(plus.device_key(1), Some(1));
+        assert_eq!(plus.device_key(2), Some(2));
+        assert_eq!(plus.device_key(3), None);
+        assert_eq!(plus.device_key(4), Some(3));
+    }
+
+    #[test]
+    fn key_map() {
+        let mini = DeckLayout::new(6, (80, 80));
+
+        let grid = KeyMap::grid(mini);
+        assert_eq!(grid.device_key(14), Some(5));
+        assert_eq!(grid.menu_key(5), Some(14));
+        assert_eq!(grid.device_key(1), None);
+
+        // Keys after the last one of the map are empty
+        let keys = KeyMap::new(mini, vec![Some(0), Some(1), None, Some(7)]);
+        assert_eq!(keys.device_key(7), Some(3));
+        assert_eq!(keys.menu_key(2), None);
+        assert_eq!(keys.menu_key(5), None);
+        assert_eq!(keys.device_key(14), None);
+    }
+}
@@ -0,0 +1,904 @@
+mod gestures;
+mod hid;
+mod icons;
+mod layout;
+mod sleep;
+#[cfg(test)]
+mod virtual_deck;
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    fmt::Display,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, RecvTimeoutError, Sender, TryRecvError},
+        Arc, Mutex, RwLock,
+    },
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use streamdeck_hid_rs::{ButtonEvent, ButtonState};
+
+use crate::image::{ImageSourceType, RgbImage};
+
+use icons::IconCache;
+
+pub use gestures::*;
+pub use layout::*;
+pub use sleep::*;
+#[cfg(test)]
+pub use virtual_deck::*;
+
+/// The hardware side of a [`Deck`], the StreamDeck itself or a [`VirtualDeck`] in tests
+pub trait DeckDevice: Send + Sync {
+    fn layout(&self) -> DeckLayout;
+
+    fn set_button_image(&self, index: u8, image: &RgbImage) -> Result<()>;
+
+    /// Sets the brightness of all keys, in percent
+    fn set_brightness(&self, percent: u8) -> Result<()>;
+
+    /// Calls `callback` for every button event, until the device is gone
+    fn read_button_events(&self, callback: &dyn Fn(ButtonEvent)) -> Result<()>;
+}
+
+/// Opens the device of a [`Deck`], again after it was unplugged
+type Open = dyn Fn() -> Result<Arc<dyn DeckDevice>> + Send + Sync;
+
+/// The menu key that goes home when it is held, back on every page
+const HOME_KEY: u8 = 0;
+
+/// Sent instead of a button event when the deck is gone for good
+const FAILURE: u32 = 0xffffffff;
+
+/// Sent instead of a button event when the deck is unplugged, and will be reconnected
+const UNPLUGGED: u32 = 0xfffffffe;
+
+// How often an unplugged deck is looked for
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Returned by [`DeckReceiver`] when back is held, menus pass it on with `?` until the main
+/// menu catches it
+#[derive(Debug)]
+pub struct GoHome;
+
+impl Display for GoHome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Back was held to go to the main menu")
+    }
+}
+
+impl std::error::Error for GoHome {}
+
+/// Both views on the button events, raw for menus that draw keys while they are held, and
+/// gestures for menus that act on them
+enum DeckInput {
+    Button(ButtonEvent),
+    Gesture(Gesture),
+}
+
+pub struct DeckReceiver {
+    deck: Deck,
+    rx: Receiver<(ButtonEvent, Instant)>,
+    gestures: RefCell<GestureDetector>,
+    pending: RefCell<VecDeque<DeckInput>>,
+
+    /// The key that went home or woke the deck up, its release must not press anything
+    swallow_up: Cell<Option<u8>>,
+
+    sleep: RefCell<SleepConfig>,
+
+    /// When the last button event came in, for going to sleep
+    last_input: Cell<Instant>,
+    asleep: Cell<bool>,
+
+    /// When the screensaver is drawn again, while the deck sleeps
+    next_frame: Cell<Option<Instant>>,
+}
+
+impl Deref for DeckReceiver {
+    type Target = Deck;
+
+    fn deref(&self) -> &Self::Target {
+        &self.deck
+    }
+}
+
+/// The StreamDeck, as seen by the menus
+///
+/// Keys are numbered on the 5x3 grid of the 15 key model, [`DeckLayout`] places them on the
+/// actual device unless a page places them with [`Self::set_key_map`]. Images are scaled to the
+/// key size of the device.
+///
+/// A deck that was opened with [`Self::open_with`] is opened again when it is unplugged, and
+/// gets back the images that were on its keys. Menus keep waiting for presses in the meantime.
+#[derive(Clone)]
+pub struct Deck {
+    device: Arc<RwLock<Arc<dyn DeckDevice>>>,
+    layout: DeckLayout,
+    keys: Arc<RwLock<KeyMap>>,
+    icons: Arc<IconCache>,
+    screen: Arc<Mutex<Screen>>,
+
+    /// Decks without it are gone for good when they are unplugged
+    reopen: Option<Arc<Open>>,
+
+    /// Whether the device is gone and [`Self::reopen`] looks for it
+    unplugged: Arc<AtomicBool>,
+    tx: Sender<(ButtonEvent, Instant)>,
+}
+
+/// What the device shows, as far as it was sent from here
+///
+/// Also while the device is gone or covered by a screensaver, so it can be put back.
+struct Screen {
+    keys: Vec<Option<KeyImage>>,
+
+    /// Brightness of the keys in percent, while the deck is awake
+    brightness: u8,
+
+    /// Brightness of the keys while the deck sleeps, see [`SleepConfig`]
+    asleep: Option<u8>,
+
+    /// The screensaver that is on the keys instead of what was sent to them, per key as far as
+    /// it is known
+    cover: Option<Vec<Option<RgbImage>>>,
+}
+
+impl Screen {
+    /// Sends all keys to `device`, the keys that nothing was sent to are made black
+    fn write_keys(&self, device: &dyn DeckDevice, (width, height): (u32, u32)) -> Result<()> {
+        let black = KeyImage::Image(RgbImage::new(width, height));
+
+        for (key, image) in self.keys.iter().enumerate() {
+            write(device, key as u8, image.as_ref().unwrap_or(&black))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An image that is on a key
+enum KeyImage {
+    Icon(Arc<RgbImage>),
+    Image(RgbImage),
+}
+
+impl PartialEq for KeyImage {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            // Every icon is only once in the cache, so they don't have to be compared pixel by pixel
+            (KeyImage::Icon(icon), KeyImage::Icon(other)) => Arc::ptr_eq(icon, other),
+            (KeyImage::Image(image), KeyImage::Image(other)) => image == other,
+            _ => false,
+        }
+    }
+}
+
+impl Deck {
+    /// Opens the first StreamDeck, and reconnects to it when it is unplugged
+    pub fn open() -> Result<DeckReceiver> {
+        Self::open_with(hid::open)
+    }
+
+    /// Opens the deck with `open`, which is called again to reconnect when it is unplugged
+    pub fn open_with(
+        open: impl Fn() -> Result<Arc<dyn DeckDevice>> + Send + Sync + 'static,
+    ) -> Result<DeckReceiver> {
+        let mut receiver = Self::with_device(open()?);
+        receiver.deck.reopen = Some(Arc::new(open));
+
+        Ok(receiver)
+    }
+
+    pub fn with_device(device: Arc<dyn DeckDevice>) -> DeckReceiver {
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        let layout = device.layout();
+        let icons = Arc::new(IconCache::new(layout.key_size));
+        let screen = Arc::new(Mutex::new(Screen {
+            keys: (0..layout.button_count()).map(|_| None).collect(),
+            brightness: 100,
+            asleep: None,
+            cover: None,
+        }));
+
+        DeckReceiver {
+            deck: Self {
+                device: Arc::new(RwLock::new(device)),
+                layout,
+                keys: Arc::new(RwLock::new(KeyMap::grid(layout))),
+                icons,
+                screen,
+                reopen: None,
+                unplugged: Arc::new(AtomicBool::new(false)),
+                tx,
+            },
+            rx,
+            gestures: RefCell::new(GestureDetector::new(GestureConfig::default())),
+            pending: RefCell::new(VecDeque::new()),
+            swallow_up: Cell::new(None),
+            sleep: RefCell::new(SleepConfig::default()),
+            last_input: Cell::new(Instant::now()),
+            asleep: Cell::new(false),
+            next_frame: Cell::new(None),
+        }
+    }
+
+    pub fn layout(&self) -> DeckLayout {
+        self.layout
+    }
+
+    /// Places the menu keys on the device with `keys`, until it is set again
+    ///
+    /// Button events come in with the menu key of `keys` too. Keys are not drawn again, that is
+    /// up to the page that sets it.
+    pub fn set_key_map(&self, keys: KeyMap) {
+        *self.keys.write().expect("deck lock poisoned") = keys;
+    }
+
+    fn device_key(&self, index: u8) -> Option<u8> {
+        self.keys
+            .read()
+            .expect("deck lock poisoned")
+            .device_key(index)
+    }
+
+    /// Sets the brightness of the keys in percent, a sleeping deck gets it when it wakes up
+    pub fn set_brightness(&self, percent: u8) -> Result<()> {
+        let mut screen = self.screen.lock().expect("deck lock poisoned");
+        screen.brightness = percent.min(100);
+
+        if screen.asleep.is_some() {
+            return Ok(());
+        }
+
+        let device = self.device.read().expect("deck lock poisoned");
+
+        self.tolerate(device.set_brightness(screen.brightness))
+    }
+
+    /// Makes all keys black, keys that already are aren't sent again
+    pub fn clear(&self) -> Result<()> {
+        self.set_page(vec![])
+    }
+
+    /// Makes menu key `index` black
+    pub fn clear_button(&self, index: u8) -> Result<()> {
+        let (width, height) = self.layout.key_size;
+
+        self.set_button_image(index, ImageSourceType::Rgb(RgbImage::new(width, height)))
+    }
+
+    /// Shows `image` on menu key `index`, keys that don't fit on the device are skipped
+    ///
+    /// JPEG icons come from the [`IconCache`].
+    pub fn set_button_image(&self, index: u8, image: ImageSourceType) -> Result<()> {
+        match self.device_key(index) {
+            Some(key) => self.send(key, image),
+            None => Ok(()),
+        }
+    }
+
+    /// Shows a whole page in one go, `images` on their menu keys and every other key black
+    ///
+    /// Only the keys that show something else than before are sent to the device.
+    pub fn set_page(&self, images: Vec<(u8, ImageSourceType)>) -> Result<()> {
+        let (width, height) = self.layout.key_size;
+        let mut keys: Vec<_> = (0..self.layout.button_count()).map(|_| None).collect();
+
+        for (index, image) in images {
+            if let Some(key) = self.device_key(index) {
+                keys[key as usize] = Some(image);
+            }
+        }
+
+        for (key, image) in keys.into_iter().enumerate() {
+            let image = image.unwrap_or_else(|| ImageSourceType::Rgb(RgbImage::new(width, height)));
+
+            self.send(key as u8, image)?;
+        }
+
+        Ok(())
+    }
+
+    /// Spreads `image` over all keys of the device, see [`DeckLayout::split`]
+    pub fn set_fullscreen_image(&self, image: ImageSourceType) -> Result<()> {
+        let split = self.layout.split(&image.decode()?);
+
+        for (idx, buffer) in split.into_iter().enumerate() {
+            self.send(idx as u8, ImageSourceType::Rgb(buffer))?;
+        }
+
+        Ok(())
+    }
+
+    /// Shows `image` on device key `key`, unless it is already on there
+    ///
+    /// JPEG icons come from the [`IconCache`].
+    fn send(&self, key: u8, image: ImageSourceType) -> Result<()> {
+        // Held while sending, so another thread can't send to the key in between
+        let mut screen = self.screen.lock().expect("deck lock poisoned");
+
+        let image = match image {
+            ImageSourceType::Jpeg(jpeg) => KeyImage::Icon(self.icons.get(jpeg)?),
+            image => KeyImage::Image(icons::fit(image.decode()?, self.layout.key_size)),
+        };
+
+        if screen.keys[key as usize].as_ref() == Some(&image) {
+            return Ok(());
+        }
+
+        // Keys under the screensaver get the image when the deck wakes up
+        if screen.cover.is_none() {
+            let device = self.device.read().expect("deck lock poisoned");
+
+            if let Err(why) = self.tolerate(write(device.as_ref(), key, &image)) {
+                // Whatever is on the key now is unknown
+                screen.keys[key as usize] = None;
+
+                return Err(why);
+            }
+        }
+
+        screen.keys[key as usize] = Some(image);
+
+        Ok(())
+    }
+
+    /// Dims the keys to `brightness`, until [`Self::wake`]
+    fn sleep(&self, brightness: u8) -> Result<()> {
+        let brightness = brightness.min(100);
+        let mut screen = self.screen.lock().expect("deck lock poisoned");
+        screen.asleep = Some(brightness);
+
+        let device = self.device.read().expect("deck lock poisoned");
+
+        self.tolerate(device.set_brightness(brightness))
+    }
+
+    /// Spreads the screensaver `image` over all keys of a sleeping deck
+    ///
+    /// Only the keys that show something else than the last time are sent to the device.
+    fn cover(&self, image: &RgbImage) -> Result<()> {
+        let mut screen = self.screen.lock().expect("deck lock poisoned");
+        let cover = screen
+            .cover
+            .get_or_insert_with(|| vec![None; self.layout.button_count()]);
+
+        let device = self.device.read().expect("deck lock poisoned");
+
+        for (key, buffer) in self.layout.split(image).into_iter().enumerate() {
+            if cover[key].as_ref() == Some(&buffer) {
+                continue;
+            }
+
+            if let Err(why) = self.tolerate(device.set_button_image(key as u8, &buffer)) {
+                cover[key] = None;
+
+                return Err(why);
+            }
+
+            cover[key] = Some(buffer);
+        }
+
+        Ok(())
+    }
+
+    /// Puts back the brightness, and the keys when a screensaver was on them
+    fn wake(&self) -> Result<()> {
+        let mut screen = self.screen.lock().expect("deck lock poisoned");
+        screen.asleep = None;
+
+        let device = self.device.read().expect("deck lock poisoned");
+        self.tolerate(device.set_brightness(screen.brightness))?;
+
+        if screen.cover.take().is_some() {
+            let result = screen.write_keys(device.as_ref(), self.layout.key_size);
+
+            if let Err(why) = self.tolerate(result) {
+                // Parts of the screensaver can still be on any key
+                screen.keys.fill_with(|| None);
+
+                return Err(why);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A deck that is unplugged gets everything back when it is reconnected, sending to it
+    /// until then doesn't fail
+    ///
+    /// Errors while the reader thread hasn't seen the device go yet are returned, the keys
+    /// that they were for have to be sent again.
+    fn tolerate(&self, result: Result<()>) -> Result<()> {
+        match result {
+            Err(_) if self.unplugged.load(Ordering::Relaxed) => Ok(()),
+            result => result,
+        }
+    }
+
+    pub fn start_event_loop(&self) {
+        std::thread::spawn({
+            let deck = self.clone();
+
+            move || deck.read_button_events()
+        });
+    }
+
+    /// Passes on the button events of the device, and of the device that replaces it every time
+    /// it is plugged in again
+    fn read_button_events(&self) -> Result<()> {
+        loop {
+            let device = self.device.read().expect("deck lock poisoned").clone();
+
+            device
+                .read_button_events(&|event| {
+                    // Keys without a menu key on them don't do anything
+                    let keys = self.keys.read().expect("deck lock poisoned");
+
+                    let Some(key) = keys.menu_key(event.button_id as u8) else {
+                        return;
+                    };
+
+                    let event = ButtonEvent {
+                        button_id: key as u32,
+                        state: event.state,
+                    };
+
+                    self.tx
+                        .send((event, Instant::now()))
+                        .expect("channel closed");
+                })
+                .ok();
+
+            let Some(reopen) = &self.reopen else {
+                let failure = ButtonEvent {
+                    button_id: FAILURE,
+                    state: ButtonState::Down,
+                };
+
+                return Ok(self.tx.send((failure, Instant::now()))?);
+            };
+
+            let unplugged = ButtonEvent {
+                button_id: UNPLUGGED,
+                state: ButtonState::Up,
+            };
+
+            self.unplugged.store(true, Ordering::Relaxed);
+            self.tx.send((unplugged, Instant::now()))?;
+            log::warn!("Lost the Stream Deck, waiting for it to come back");
+
+            loop {
+                sleep(RECONNECT_INTERVAL);
+
+                match reopen() {
+                    Ok(device) if device.layout() == self.layout => {
+                        self.restore(device);
+                        break;
+                    }
+                    Ok(_) => log::warn!("Another kind of Stream Deck was plugged in, ignoring it"),
+                    Err(_) => {}
+                }
+            }
+        }
+    }
+
+    /// Replaces the device with `device`, and puts back what was on the keys of the old one
+    fn restore(&self, device: Arc<dyn DeckDevice>) {
+        let mut screen = self.screen.lock().expect("deck lock poisoned");
+
+        // When it is gone again already, the next reconnect tries again. A screensaver is put
+        // back when it is drawn again.
+        device
+            .set_brightness(screen.asleep.unwrap_or(screen.brightness))
+            .ok();
+
+        match &mut screen.cover {
+            // The new device shows none of it
+            Some(cover) => cover.fill(None),
+            None => {
+                screen
+                    .write_keys(device.as_ref(), self.layout.key_size)
+                    .ok();
+            }
+        }
+
+        *self.device.write().expect("deck lock poisoned") = device;
+        self.unplugged.store(false, Ordering::Relaxed);
+    }
+}
+
+fn write(device: &dyn DeckDevice, key: u8, image: &KeyImage) -> Result<()> {
+    match image {
+        KeyImage::Icon(icon) => device.set_button_image(key, icon),
+        KeyImage::Image(image) => device.set_button_image(key, image),
+    }
+}
+
+impl DeckReceiver {
+    /// Sets the thresholds of the gestures, see [`GestureConfig`]
+    pub fn set_gestures(&self, config: GestureConfig) {
+        self.gestures.borrow_mut().set_config(config);
+    }
+
+    /// Sets when the deck goes to sleep, see [`SleepConfig`]
+    ///
+    /// The deck only sleeps while a menu waits for a button event or a gesture.
+    pub fn set_sleep(&self, config: SleepConfig) {
+        *self.sleep.borrow_mut() = config;
+        self.last_input.set(Instant::now());
+    }
+
+    /// The next time a button goes down or up
+    ///
+    /// Returns [`GoHome`] when back is held, its release is not returned after that.
+    pub fn next_btn_event(&self) -> Result<ButtonEvent> {
+        loop {
+            match self.next_input()? {
+                DeckInput::Button(event) => return Ok(event),
+
+                DeckInput::Gesture(gesture) => self.go_home(gesture)?,
+            }
+        }
+    }
+
+    /// The next gesture, waits for double-taps and long-presses to be told apart
+    ///
+    /// Returns [`GoHome`] when back is held, like [`Self::next_btn_event`].
+    pub fn next_gesture(&self) -> Result<Gesture> {
+        loop {
+            if let DeckInput::Gesture(gesture) = self.next_input()? {
+                self.go_home(gesture)?;
+
+                return Ok(gesture);
+            }
+        }
+    }
+
+    fn go_home(&self, gesture: Gesture) -> Result<()> {
+        if is_home(gesture) {
+            return Err(GoHome.into());
+        }
+
+        Ok(())
+    }
+
+    fn next_input(&self) -> Result<DeckInput> {
+        loop {
+            if let Some(input) = self.pending.borrow_mut().pop_front() {
+                return Ok(input);
+            }
+
+            let deadline = [self.gestures.borrow().deadline(), self.sleep_deadline()]
+                .into_iter()
+                .flatten()
+                .min();
+
+            let received = match deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+
+                    match self.rx.recv_timeout(timeout) {
+                        Ok(received) => Some(received),
+                        Err(RecvTimeoutError::Timeout) => None,
+                        Err(RecvTimeoutError::Disconnected) => Err(RecvTimeoutError::Disconnected)?,
+                    }
+                }
+
+                None => Some(self.rx.recv()?),
+            };
+
+            match received {
+                Some((event, at)) => self.receive(event, at)?,
+                None => {
+                    let now = Instant::now();
+                    let gestures = self.gestures.borrow_mut().tick(now);
+
+                    self.queue(gestures);
+                    self.doze(now)?;
+                }
+            }
+        }
+    }
+
+    /// Queues `event` and the gestures it completes
+    fn receive(&self, event: ButtonEvent, at: Instant) -> Result<()> {
+        if event.button_id == FAILURE {
+            return Err(anyhow!("HID communication failure"));
+        }
+
+        // Keys that are held when the deck is unplugged never come up
+        if event.button_id == UNPLUGGED {
+            self.gestures.borrow_mut().reset();
+            self.swallow_up.set(None);
+
+            return Ok(());
+        }
+
+        self.last_input.set(at);
+
+        // The press that wakes the deck up doesn't press anything
+        if self.asleep.get() {
+            if matches!(event.state, ButtonState::Down) {
+                self.asleep.set(false);
+                self.next_frame.set(None);
+                self.swallow_up.set(Some(event.button_id as u8));
+
+                self.deck.wake()?;
+            }
+
+            return Ok(());
+        }
+
+        // A key that is let go late was long-pressed before it came up
+        let expired = self.gestures.borrow_mut().tick(at);
+        self.queue(expired);
+
+        let id = Some(event.button_id as u8);
+
+        if matches!(event.state, ButtonState::Up) && self.swallow_up.get() == id {
+            self.swallow_up.set(None);
+        } else {
+            self.pending
+                .borrow_mut()
+                .push_back(DeckInput::Button(event));
+        }
+
+        let gestures = self.gestures.borrow_mut().event(event, at);
+        self.queue(gestures);
+
+        Ok(())
+    }
+
+    /// When the deck goes to sleep, or when the screensaver is drawn again while it sleeps
+    fn sleep_deadline(&self) -> Option<Instant> {
+        if self.asleep.get() {
+            return self.next_frame.get();
+        }
+
+        self.sleep
+            .borrow()
+            .idle
+            .map(|idle| self.last_input.get() + idle)
+    }
+
+    /// Goes to sleep or draws the screensaver, when it is time for it at `now`
+    fn doze(&self, now: Instant) -> Result<()> {
+        if self.sleep_deadline().is_none_or(|deadline| now < deadline) {
+            return Ok(());
+        }
+
+        let sleep = self.sleep.borrow();
+
+        if !self.asleep.replace(true) {
+            // Keys that are held now come up while it sleeps, when nothing looks at them
+            self.gestures.borrow_mut().reset();
+            self.deck.sleep(sleep.brightness)?;
+        }
+
+        if let Some(screensaver) = &sleep.screensaver {
+            self.deck.cover(&(screensaver.draw)(self.layout)?)?;
+            self.next_frame.set(Some(now + screensaver.interval));
+        }
+
+        Ok(())
+    }
+
+    fn queue(&self, gestures: Vec<Gesture>) {
+        for gesture in gestures {
+            if is_home(gesture) {
+                self.swallow_up.set(Some(HOME_KEY));
+            }
+
+            self.pending
+                .borrow_mut()
+                .push_back(DeckInput::Gesture(gesture));
+        }
+    }
+
+    pub fn wait_for_any_press(&self) -> Result<()> {
+        loop {
+            let event = self.next_btn_event()?;
+
+            if matches!(event.state, ButtonState::Up) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drops the events that came in so far, keys that are still held keep making gestures
+    pub fn flush_btn_events(&self) -> Result<()> {
+        loop {
+            match self.rx.try_recv() {
+                Err(TryRecvError::Empty) => {
+                    self.pending.borrow_mut().clear();
+
+                    return Ok(());
+                }
+
+                Err(TryRecvError::Disconnected) => {
+                    Err(TryRecvError::Disconnected)?;
+                }
+
+                Ok((event, at)) => {
+                    if event.button_id != FAILURE {
+                        self.receive(event, at)?;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn device(&self) -> Deck {
+        self.deck.clone()
+    }
+}
+
+fn is_home(gesture: Gesture) -> bool {
+    gesture.button_id == HOME_KEY && gesture.kind == GestureKind::LongPress
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgb;
+
+    use super::*;
+
+    const CAMERA: &[u8] = include_bytes!("../../images/camera.jpg");
+    const IMG_CAMERA: ImageSourceType = ImageSourceType::Jpeg(CAMERA);
+
+    // Long enough for a slow CI machine, the deck is looked for again every second
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn only_changed_keys_are_sent() {
+        let (deck, virtual_deck) = VirtualDeck::open();
+        let red = RgbImage::from_pixel(72, 72, Rgb([255, 0, 0]));
+
+        // Nothing is known about the keys at first
+        deck.clear().unwrap();
+        assert_eq!(virtual_deck.writes(), 15);
+
+        deck.clear().unwrap();
+        assert_eq!(virtual_deck.writes(), 15);
+
+        let page = || vec![(0, IMG_CAMERA), (1, ImageSourceType::Rgb(red.clone()))];
+
+        deck.set_page(page()).unwrap();
+        assert_eq!(virtual_deck.writes(), 17);
+
+        deck.set_page(page()).unwrap();
+        deck.set_button_image(0, IMG_CAMERA).unwrap();
+        assert_eq!(virtual_deck.writes(), 17);
+
+        // Only the key that is left out is cleared
+        deck.set_page(vec![(0, IMG_CAMERA)]).unwrap();
+        assert_eq!(virtual_deck.writes(), 18);
+        assert!(virtual_deck.key(1) == RgbImage::new(72, 72));
+    }
+
+    #[test]
+    fn replugged_deck_gets_its_keys_back() {
+        let (deck, virtual_deck) = VirtualDeck::reconnecting(DeckLayout::new(15, (72, 72)));
+        let red = RgbImage::from_pixel(72, 72, Rgb([255, 0, 0]));
+        let blue = RgbImage::from_pixel(72, 72, Rgb([0, 0, 255]));
+
+        deck.set_page(vec![
+            (0, IMG_CAMERA),
+            (1, ImageSourceType::Rgb(red.clone())),
+        ])
+        .unwrap();
+
+        // Back is held when the deck goes, that is not a long-press while it is gone
+        virtual_deck.send(0, ButtonState::Down);
+        virtual_deck.unplug();
+
+        // Until the deck knows that the device is gone, sending to it fails
+        assert!(deck
+            .set_button_image(1, ImageSourceType::Rgb(blue.clone()))
+            .is_err());
+
+        deck.start_event_loop();
+
+        let start = Instant::now();
+
+        while !deck.unplugged.load(Ordering::Relaxed) {
+            assert!(start.elapsed() < TIMEOUT, "the deck was not unplugged");
+            sleep(Duration::from_millis(5));
+        }
+
+        deck.set_button_image(2, ImageSourceType::Rgb(blue.clone()))
+            .unwrap();
+        virtual_deck.plug_in();
+
+        // The key that failed is black, the rest is back as it was sent
+        assert!(virtual_deck.wait_for_key(2, &blue, TIMEOUT));
+        assert!(virtual_deck.key(0) == *deck.icons.get(CAMERA).unwrap());
+        assert!(virtual_deck.key(1) == RgbImage::new(72, 72));
+
+        deck.set_button_image(1, ImageSourceType::Rgb(red.clone()))
+            .unwrap();
+        assert!(virtual_deck.key(1) == red);
+
+        // Back went down before it was unplugged, but it never goes home
+        virtual_deck.press(1);
+
+        while deck.next_gesture().unwrap().button_id != 1 {}
+    }
+
+    #[test]
+    fn press_wakes_sleeping_deck() {
+        let (deck, virtual_deck) = VirtualDeck::open();
+        let red = RgbImage::from_pixel(72, 72, Rgb([255, 0, 0]));
+        let blue = RgbImage::from_pixel(72, 72, Rgb([0, 0, 255]));
+
+        deck.set_brightness(80).unwrap();
+        deck.set_button_image(1, ImageSourceType::Rgb(blue.clone()))
+            .unwrap();
+        deck.set_sleep(SleepConfig {
+            idle: Some(Duration::from_millis(200)),
+            brightness: 150,
+            screensaver: Some(Screensaver {
+                interval: Duration::from_millis(50),
+                draw: Box::new(|layout| {
+                    let (width, height) = layout.screen_size();
+
+                    Ok(RgbImage::from_pixel(width, height, Rgb([255, 0, 0])))
+                }),
+            }),
+        });
+        deck.start_event_loop();
+
+        let start = Instant::now();
+        virtual_deck.send(2, ButtonState::Down);
+
+        let gestures = std::thread::spawn(move || {
+            let mut gestures = vec![];
+
+            while gestures.last() != Some(&(3, GestureKind::Press)) {
+                let gesture = deck.next_gesture().unwrap();
+                gestures.push((gesture.button_id, gesture.kind));
+            }
+
+            gestures
+        });
+
+        assert!(virtual_deck.wait_for_key(1, &red, TIMEOUT));
+        assert_eq!(virtual_deck.brightness(), 100);
+
+        // A screensaver that stays the same isn't sent again
+        let writes = virtual_deck.writes();
+        sleep(Duration::from_millis(200));
+        assert_eq!(virtual_deck.writes(), writes);
+
+        // The key that was held when it fell asleep comes up, long after it would be a
+        // long-press
+        virtual_deck.send(2, ButtonState::Up);
+        sleep(Duration::from_millis(800).saturating_sub(start.elapsed()));
+
+        // Waking up puts the keys back, without pressing the key under the finger
+        virtual_deck.press(1);
+        assert!(virtual_deck.wait_for_key(1, &blue, TIMEOUT));
+        assert_eq!(virtual_deck.brightness(), 80);
+
+        virtual_deck.press(3);
+
+        assert_eq!(
+            gestures.join().unwrap(),
+            [
+                (2, GestureKind::Down),
+                (3, GestureKind::Down),
+                (3, GestureKind::Press)
+            ]
+        );
+    }
+}
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::image::RgbImage;
+
+use super::DeckLayout;
+
+/// When the deck goes to sleep, and what it shows while it sleeps
+///
+/// The first press on a sleeping deck only wakes it up, it doesn't press anything.
+pub struct SleepConfig {
+    /// Without button events for this long the deck goes to sleep, `None` keeps it awake
+    pub idle: Option<Duration>,
+
+    /// Brightness of the keys while the deck sleeps, in percent
+    pub brightness: u8,
+
+    /// Shown over all keys while the deck sleeps, the keys are dimmed as they are without it
+    pub screensaver: Option<Screensaver>,
+}
+
+impl Default for SleepConfig {
+    fn default() -> Self {
+        Self {
+            idle: None,
+            brightness: 10,
+            screensaver: None,
+        }
+    }
+}
+
+/// A picture of the size of all keys together, see [`DeckLayout::screen_size`]
+pub struct Screensaver {
+    /// How often the picture is drawn again
+    pub interval: Duration,
+    pub draw: Box<dyn Fn(DeckLayout) -> Result<RgbImage> + Send>,
+}
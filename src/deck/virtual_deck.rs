@@ -0,0 +1,248 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+        mpsc::{Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use streamdeck_hid_rs::{ButtonEvent, ButtonState};
+
+use crate::image::RgbImage;
+
+use super::{Deck, DeckDevice, DeckLayout, DeckReceiver};
+
+// How often `wait_for_key` looks at the key again
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A StreamDeck that only exists in memory, to run the menus without the USB device
+///
+/// It keeps the last image that was sent to every key, and button events are sent to it from
+/// the test. Keys are numbered like on the device, not like in the menus. [`Self::unplug`]
+/// makes it behave like a device that was disconnected.
+pub struct VirtualDeck {
+    layout: DeckLayout,
+    keys: Arc<Mutex<Vec<RgbImage>>>,
+    writes: Arc<AtomicUsize>,
+    brightness: Arc<AtomicU8>,
+    events: Mutex<Option<Sender<ButtonEvent>>>,
+
+    /// Set when the device that was plugged in last is unplugged
+    gone: Mutex<Arc<AtomicBool>>,
+
+    /// The device of a deck that was plugged in, until the [`Deck`] opens it
+    plugged: Arc<Mutex<Option<VirtualDevice>>>,
+}
+
+struct VirtualDevice {
+    layout: DeckLayout,
+    keys: Arc<Mutex<Vec<RgbImage>>>,
+
+    /// How many images were sent to the keys
+    writes: Arc<AtomicUsize>,
+    brightness: Arc<AtomicU8>,
+    events: Mutex<Receiver<ButtonEvent>>,
+
+    /// Nothing can be sent to a device that is unplugged
+    gone: Arc<AtomicBool>,
+}
+
+impl VirtualDeck {
+    /// Opens a virtual 15 key deck, and the [`DeckReceiver`] that the menus use to talk to it
+    pub fn open() -> (DeckReceiver, Self) {
+        Self::with_layout(DeckLayout::new(15, (72, 72)))
+    }
+
+    pub fn with_layout(layout: DeckLayout) -> (DeckReceiver, Self) {
+        let deck = Self::unplugged(layout);
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        *deck.events.lock().expect("virtual deck lock poisoned") = Some(tx);
+
+        (Deck::with_device(Arc::new(deck.device(rx))), deck)
+    }
+
+    /// Opens a virtual deck that can be plugged in again with [`Self::plug_in`], like
+    /// [`Deck::open`] does for the real one
+    pub fn reconnecting(layout: DeckLayout) -> (DeckReceiver, Self) {
+        let deck = Self::unplugged(layout);
+        deck.plug_in();
+
+        let plugged = deck.plugged.clone();
+        let receiver = Deck::open_with(move || {
+            let device = plugged
+                .lock()
+                .expect("virtual deck lock poisoned")
+                .take()
+                .ok_or(anyhow!("Virtual deck is unplugged"))?;
+
+            Ok(Arc::new(device))
+        })
+        .expect("virtual deck is plugged in");
+
+        (receiver, deck)
+    }
+
+    fn unplugged(layout: DeckLayout) -> Self {
+        let (width, height) = layout.key_size;
+
+        Self {
+            layout,
+            keys: Arc::new(Mutex::new(vec![
+                RgbImage::new(width, height);
+                layout.button_count()
+            ])),
+            writes: Arc::new(AtomicUsize::new(0)),
+            brightness: Arc::new(AtomicU8::new(100)),
+            events: Mutex::new(None),
+            gone: Mutex::new(Arc::new(AtomicBool::new(true))),
+            plugged: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// The device side, that gets its button events from `events`
+    fn device(&self, events: Receiver<ButtonEvent>) -> VirtualDevice {
+        let gone = Arc::new(AtomicBool::new(false));
+        *self.gone.lock().expect("virtual deck lock poisoned") = gone.clone();
+
+        VirtualDevice {
+            layout: self.layout,
+            keys: self.keys.clone(),
+            writes: self.writes.clone(),
+            brightness: self.brightness.clone(),
+            events: Mutex::new(events),
+            gone,
+        }
+    }
+
+    pub fn send(&self, button_id: u8, state: ButtonState) {
+        let events = self.events.lock().expect("virtual deck lock poisoned");
+
+        if let Some(events) = events.as_ref() {
+            events
+                .send(ButtonEvent {
+                    button_id: button_id as u32,
+                    state,
+                })
+                .ok();
+        }
+    }
+
+    /// Pushes the button down and lets it go again
+    pub fn press(&self, button_id: u8) {
+        self.send(button_id, ButtonState::Down);
+        self.send(button_id, ButtonState::Up);
+    }
+
+    /// The image that is currently on key `index`
+    pub fn key(&self, index: u8) -> RgbImage {
+        self.keys.lock().expect("virtual deck lock poisoned")[index as usize].clone()
+    }
+
+    /// How many images were sent to the keys, since the deck was opened
+    pub fn writes(&self) -> usize {
+        self.writes.load(Ordering::Relaxed)
+    }
+
+    /// Brightness of the keys in percent
+    pub fn brightness(&self) -> u8 {
+        self.brightness.load(Ordering::Relaxed)
+    }
+
+    /// Waits at most `timeout` until key `index` shows `expected`, returns whether it did
+    pub fn wait_for_key(&self, index: u8, expected: &RgbImage, timeout: Duration) -> bool {
+        let start = Instant::now();
+
+        while start.elapsed() < timeout {
+            if self.key(index) == *expected {
+                return true;
+            }
+
+            sleep(POLL_INTERVAL);
+        }
+
+        false
+    }
+
+    /// Disconnects the deck, after the events that were already sent
+    pub fn unplug(&self) {
+        self.gone
+            .lock()
+            .expect("virtual deck lock poisoned")
+            .store(true, Ordering::Relaxed);
+
+        self.events
+            .lock()
+            .expect("virtual deck lock poisoned")
+            .take();
+    }
+
+    /// Connects the deck again, with black keys like a StreamDeck that was just plugged in
+    pub fn plug_in(&self) {
+        let (width, height) = self.layout.key_size;
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        for key in self
+            .keys
+            .lock()
+            .expect("virtual deck lock poisoned")
+            .iter_mut()
+        {
+            *key = RgbImage::new(width, height);
+        }
+
+        *self.events.lock().expect("virtual deck lock poisoned") = Some(tx);
+        *self.plugged.lock().expect("virtual deck lock poisoned") = Some(self.device(rx));
+    }
+}
+
+impl VirtualDevice {
+    fn check_connected(&self) -> Result<()> {
+        match self.gone.load(Ordering::Relaxed) {
+            true => Err(anyhow!("Virtual deck is unplugged")),
+            false => Ok(()),
+        }
+    }
+}
+
+impl DeckDevice for VirtualDevice {
+    fn layout(&self) -> DeckLayout {
+        self.layout
+    }
+
+    fn set_button_image(&self, index: u8, image: &RgbImage) -> Result<()> {
+        self.check_connected()?;
+
+        let mut keys = self.keys.lock().expect("virtual deck lock poisoned");
+
+        let key = keys
+            .get_mut(index as usize)
+            .ok_or(anyhow!("Virtual deck has no key {index}"))?;
+
+        *key = image.clone();
+        self.writes.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    fn set_brightness(&self, percent: u8) -> Result<()> {
+        self.check_connected()?;
+        self.brightness.store(percent, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    fn read_button_events(&self, callback: &dyn Fn(ButtonEvent)) -> Result<()> {
+        let events = self.events.lock().expect("virtual deck lock poisoned");
+
+        // Stops when the deck is unplugged
+        for event in events.iter() {
+            callback(event);
+        }
+
+        Ok(())
+    }
+}
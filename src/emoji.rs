@@ -32,6 +32,7 @@ struct RawPack {
     data: Vec<u8>,
 }
 
+#[derive(Default)]
 pub struct EmojiPack {
     names: HashMap<String, String>,
     codepoints: HashMap<String, u64>,
@@ -1,24 +1,16 @@
-use std::io::Cursor;
-
 use anyhow::{anyhow, Result};
 use image::{
-    codecs::{
-        bmp::BmpDecoder,
-        jpeg::{JpegDecoder, JpegEncoder},
-    },
+    codecs::jpeg::{JpegDecoder, JpegEncoder},
     imageops::FilterType,
 };
 
 // Re-export from crate with same name
 pub use image::*;
 
-const FRAME_DIM: usize = 72;
-const FRAME_SIZE: usize = FRAME_DIM * FRAME_DIM;
-const DECK_WIDTH: usize = 384;
-// const DECK_HEIGHT: usize = 216;
-
+#[derive(Clone)]
 pub enum ImageSourceType<'a> {
-    Jpeg(&'a [u8]),
+    /// An image that is built into the app, these are cached by the deck
+    Jpeg(&'static [u8]),
     JpegVec(Vec<u8>),
     RawRgb(&'a [u8]),
     Rgb(RgbImage),
@@ -34,6 +26,24 @@ impl<'a> ImageSourceType<'a> {
         })
     }
 
+    /// Decodes the image at its own size, raw RGB data is taken to be a square
+    pub fn decode(self) -> Result<RgbImage> {
+        match self {
+            ImageSourceType::Jpeg(jpeg) => {
+                Ok(load_from_memory_with_format(jpeg, ImageFormat::Jpeg)?.into_rgb8())
+            }
+            ImageSourceType::JpegVec(jpeg) => {
+                Ok(load_from_memory_with_format(&jpeg, ImageFormat::Jpeg)?.into_rgb8())
+            }
+            ImageSourceType::RawRgb(raw) => {
+                let size = ((raw.len() / 3) as f64).sqrt() as u32;
+
+                ImageSourceType::RawRgb(raw).to_rgb(size, size)
+            }
+            ImageSourceType::Rgb(rgb) => Ok(rgb),
+        }
+    }
+
     pub fn to_rgb(self, width: u32, height: u32) -> Result<RgbImage> {
         Ok(match self {
             ImageSourceType::Jpeg(_) | ImageSourceType::JpegVec(_) | ImageSourceType::RawRgb(_) => {
@@ -58,14 +68,6 @@ pub fn resize(image: &RgbImage, nwidth: u32, nheight: u32, hq: bool) -> RgbImage
     )
 }
 
-pub fn decode_bmp(image: &[u8]) -> Result<Vec<u8>> {
-    let decoder = BmpDecoder::new(Cursor::new(image))?;
-    let mut buffer = vec![0; decoder.total_bytes() as usize];
-    decoder.read_image(&mut buffer)?;
-
-    Ok(buffer)
-}
-
 pub fn decode_jpeg(image: &[u8]) -> Result<Vec<u8>> {
     let decoder = JpegDecoder::new(image)?;
     let mut buffer = vec![0; decoder.total_bytes() as usize];
@@ -82,37 +84,6 @@ pub fn encode_jpeg(image: &[u8], width: u32, height: u32) -> Result<Vec<u8>> {
     Ok(buf)
 }
 
-pub fn split_raw_full_image(raw_image: &[u8]) -> Option<Vec<ImageBuffer<Rgb<u8>, Vec<u8>>>> {
-    let mut buffers = vec![];
-
-    for i in 0..15 {
-        let mut frame = [0u8; FRAME_SIZE * 3];
-
-        let row_idx = i / 5;
-        let col_idx = i % 5;
-
-        let start_row = row_idx * FRAME_DIM;
-        let start_col = col_idx * FRAME_DIM;
-
-        for j in 0..FRAME_DIM {
-            let start_idx = (start_row + j) * DECK_WIDTH + start_col + 12;
-            let end_idx = start_idx + FRAME_DIM;
-
-            let target_start_idx = j * FRAME_DIM;
-            let target_end_idx = (j + 1) * FRAME_DIM;
-
-            frame[target_start_idx * 3..target_end_idx * 3]
-                .copy_from_slice(&raw_image[start_idx * 3..end_idx * 3]);
-        }
-
-        let buffer = ImageBuffer::<Rgb<u8>, _>::from_raw(72, 72, frame[..].to_vec())?;
-
-        buffers.push(buffer);
-    }
-
-    Some(buffers)
-}
-
 fn hue_to_rgb(p: f32, q: f32, mut t: f32) -> f32 {
     if t < 0.0 {
         t += 1.0;
@@ -1,3 +1,4 @@
+pub mod audio;
 pub mod camera;
 pub mod cloud;
 pub mod deck;
@@ -8,25 +9,140 @@ pub mod menus;
 pub mod render;
 pub mod state;
 
-use std::time::{Duration, Instant};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
-use crate::deck::Deck;
+use crate::deck::{Deck, GestureConfig, Screensaver, SleepConfig};
 use anyhow::Result;
+use argh::FromArgs;
+use audio::AudioSource;
+use camera::CameraSource;
 use image::ImageSourceType;
 use libcamera::logging::{log_set_target, LoggingTarget};
-use matrix::{animations::StartupAnimation, Matrix};
+use matrix::{
+    animations::{self, EyesControl, StartupAnimation},
+    color_utils::Palette,
+    Matrix,
+};
 use state::{AppState, MatrixAnimation};
 
 const SD_ERROR_IMAGE: ImageSourceType =
     ImageSourceType::Jpeg(include_bytes!("../images/error.jpg"));
 
+/// Party lights on the LED matrix, controlled from the StreamDeck
+#[derive(FromArgs)]
+struct Args {
+    /// ALSA capture device the music visualizers listen to
+    #[argh(option, default = "String::from(\"default\")")]
+    audio_device: String,
+
+    /// WAV file to visualize instead of the capture device, played in a loop
+    #[argh(option)]
+    audio_file: Option<PathBuf>,
+
+    /// holding a key this many milliseconds is a long-press, holding back goes to the main menu
+    #[argh(option, default = "600")]
+    long_press_ms: u64,
+
+    /// tapping a key twice within this many milliseconds is a double-tap, 0 turns them off
+    #[argh(option, default = "0")]
+    double_tap_ms: u64,
+
+    /// keys that go down within this many milliseconds of each other are a chord
+    #[argh(option, default = "100")]
+    chord_ms: u64,
+
+    /// brightness of the deck keys in percent
+    #[argh(option, default = "100")]
+    brightness: u8,
+
+    /// without presses for this many seconds the deck goes to sleep, 0 keeps it awake
+    #[argh(option, default = "300")]
+    sleep_secs: u64,
+
+    /// brightness of the deck keys in percent while the deck sleeps
+    #[argh(option, default = "10")]
+    sleep_brightness: u8,
+
+    /// show the time over all deck keys while the deck sleeps
+    #[argh(switch)]
+    sleep_clock: bool,
+
+    #[argh(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Sheet(SheetCommand),
+}
+
+/// Write a sprite sheet manifest for a PNG with a grid of frames, next to the PNG
+#[derive(FromArgs)]
+#[argh(subcommand, name = "sheet")]
+struct SheetCommand {
+    /// PNG with the frames, read left to right and top to bottom
+    #[argh(positional)]
+    image: PathBuf,
+
+    /// width of a single frame in pixels
+    #[argh(option, default = "64")]
+    width: u32,
+
+    /// height of a single frame in pixels
+    #[argh(option, default = "32")]
+    height: u32,
+
+    /// how long every frame is shown in milliseconds
+    #[argh(option, default = "100")]
+    duration: u64,
+
+    /// name of the next frame, can be repeated
+    #[argh(option)]
+    name: Vec<String>,
+}
+
 fn main() -> Result<()> {
+    let args: Args = argh::from_env();
+
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    if let Some(Command::Sheet(sheet)) = args.command {
+        return animations::convert_png(
+            sheet.image,
+            sheet.width,
+            sheet.height,
+            Duration::from_millis(sheet.duration),
+            &sheet.name,
+        );
+    }
+
     log_set_target(LoggingTarget::None).ok();
 
     let matrix = Matrix::open()?;
     let deck = Deck::open()?;
     let animation = StartupAnimation::load()?;
 
+    deck.set_gestures(GestureConfig {
+        long_press: Duration::from_millis(args.long_press_ms),
+        double_tap: (args.double_tap_ms > 0).then(|| Duration::from_millis(args.double_tap_ms)),
+        chord: Duration::from_millis(args.chord_ms),
+    });
+
+    deck.set_sleep(SleepConfig {
+        idle: (args.sleep_secs > 0).then(|| Duration::from_secs(args.sleep_secs)),
+        brightness: args.sleep_brightness,
+        screensaver: args.sleep_clock.then(|| Screensaver {
+            interval: Duration::from_secs(5),
+            draw: Box::new(menus::main::clock_screensaver),
+        }),
+    });
+
+    deck.set_brightness(args.brightness)?;
+
     deck.start_event_loop();
     matrix.set_animation(Box::new(animation))?;
 
@@ -34,6 +150,10 @@ fn main() -> Result<()> {
 
     // Perform loading tasks here
     let emojis = emoji::load_emojis()?;
+    let audio = audio::start(match args.audio_file {
+        Some(path) => AudioSource::Wav(path),
+        None => AudioSource::Alsa(args.audio_device),
+    });
 
     // Make sure animation takes at least 3 secs
     std::thread::sleep(
@@ -50,6 +170,11 @@ fn main() -> Result<()> {
         emojis,
 
         matrix_animation: MatrixAnimation::Sequence,
+        palette: Palette::default(),
+        speed: 1.0,
+        audio,
+        eyes: EyesControl::new(),
+        camera: CameraSource::Libcamera,
     };
 
     state.start_matrix_animation();
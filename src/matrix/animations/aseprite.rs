@@ -0,0 +1,772 @@
+//! Reads [Aseprite](https://www.aseprite.org) files, and plays their tags on the matrix
+//!
+//! Follows the [file format spec](https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md).
+//! Only what is needed for playback is kept: visible layers with normal blending, their cels,
+//! frame durations and tags. Tilemaps are skipped.
+
+use std::{io::Read, path::Path, path::PathBuf, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use flate2::read::ZlibDecoder;
+use image::{imageops, Rgb, RgbImage, Rgba, RgbaImage};
+
+use super::{Animation, AnimationContext};
+
+pub const SPRITES_DIR: &str = "sprites";
+
+const FILE_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+
+const CHUNK_OLD_PALETTE: u16 = 0x0004;
+const CHUNK_LAYER: u16 = 0x2004;
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_TAGS: u16 = 0x2018;
+const CHUNK_PALETTE: u16 = 0x2019;
+
+const LAYER_VISIBLE: u16 = 1;
+const LAYER_GROUP: u16 = 1;
+const BLEND_NORMAL: u16 = 0;
+
+// Layer opacity is only stored when this header flag is set
+const HEADER_LAYER_OPACITY: u32 = 1;
+
+// Sprites are made for the 64x32 matrix, larger canvases are not read so a file can't make
+// every frame take gigabytes
+const MAX_CANVAS: u32 = 1024;
+
+// Indexed cels have a byte per pixel, so colors past these are never used
+const PALETTE_SIZE: usize = 256;
+
+/// Little endian reader over the bytes of a file, with the types of the spec
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .pos
+            .checked_add(count)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or(anyhow!("Unexpected end of file at byte {}", self.pos))?;
+
+        self.pos += count;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, count: usize) -> Result<()> {
+        self.bytes(count).map(|_| ())
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn short(&mut self) -> Result<i16> {
+        Ok(i16::from_le_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn dword(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.word()? as usize;
+
+        Ok(String::from_utf8_lossy(self.bytes(len)?).into_owned())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ColorDepth {
+    Rgba,
+    Grayscale,
+    Indexed,
+}
+
+impl ColorDepth {
+    fn bytes_per_pixel(&self) -> usize {
+        match self {
+            Self::Rgba => 4,
+            Self::Grayscale => 2,
+            Self::Indexed => 1,
+        }
+    }
+}
+
+pub struct AsepriteLayer {
+    pub name: String,
+
+    /// Hidden layers, layers in a hidden group and layers with another blend mode than normal
+    /// are not drawn
+    pub visible: bool,
+    pub opacity: u8,
+}
+
+struct Cel {
+    layer: usize,
+    x: i32,
+    y: i32,
+    opacity: u8,
+    image: RgbaImage,
+}
+
+pub struct AsepriteFrame {
+    pub duration: Duration,
+    cels: Vec<Cel>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TagDirection {
+    Forward,
+    Reverse,
+    PingPong,
+    PingPongReverse,
+}
+
+pub struct AsepriteTag {
+    pub name: String,
+
+    /// First and last frame, both included
+    pub from: usize,
+    pub to: usize,
+    pub direction: TagDirection,
+
+    /// How often the tag plays, 0 means forever
+    pub repeat: u16,
+}
+
+pub struct AsepriteFile {
+    pub width: u32,
+    pub height: u32,
+
+    pub layers: Vec<AsepriteLayer>,
+    pub frames: Vec<AsepriteFrame>,
+    pub tags: Vec<AsepriteTag>,
+}
+
+impl AsepriteFile {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data =
+            std::fs::read(path).with_context(|| format!("Cannot read {}", path.display()))?;
+
+        Self::parse(&data).with_context(|| format!("Invalid Aseprite file {}", path.display()))
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+
+        reader.dword()?;
+        if reader.word()? != FILE_MAGIC {
+            return Err(anyhow!("Not an Aseprite file"));
+        }
+
+        let frame_count = reader.word()? as usize;
+        let width = reader.word()? as u32;
+        let height = reader.word()? as u32;
+        let depth = match reader.word()? {
+            32 => ColorDepth::Rgba,
+            16 => ColorDepth::Grayscale,
+            8 => ColorDepth::Indexed,
+            depth => return Err(anyhow!("Unsupported color depth {depth}")),
+        };
+
+        if width > MAX_CANVAS || height > MAX_CANVAS {
+            return Err(anyhow!(
+                "Canvas of {width}x{height} is larger than {MAX_CANVAS}x{MAX_CANVAS}"
+            ));
+        }
+
+        let flags = reader.dword()?;
+        reader.skip(2 + 4 + 4)?;
+        let transparent_index = reader.byte()?;
+        reader.skip(128 - 29)?;
+
+        let mut file = Self {
+            width,
+            height,
+
+            layers: vec![],
+            frames: Vec::with_capacity(frame_count),
+            tags: vec![],
+        };
+
+        let mut palette = vec![Rgba([0, 0, 0, 255]); PALETTE_SIZE];
+
+        // Visibility of the groups that the next layer can be in, by child level
+        let mut groups_visible: Vec<bool> = vec![];
+
+        for _ in 0..frame_count {
+            let frame_size = reader.dword()? as usize;
+            let frame_end = (reader.pos - 4)
+                .checked_add(frame_size)
+                .ok_or(anyhow!("Invalid frame size {frame_size}"))?;
+
+            if reader.word()? != FRAME_MAGIC {
+                return Err(anyhow!("Invalid frame at byte {}", reader.pos - 2));
+            }
+
+            let old_chunks = reader.word()? as u32;
+            let duration = Duration::from_millis(reader.word()? as u64);
+            reader.skip(2)?;
+            let chunks = match reader.dword()? {
+                0 => old_chunks,
+                chunks => chunks,
+            };
+
+            let mut frame = AsepriteFrame {
+                duration,
+                cels: vec![],
+            };
+
+            for _ in 0..chunks {
+                let chunk_size = reader.dword()? as usize;
+                let chunk_end = (reader.pos - 4)
+                    .checked_add(chunk_size)
+                    .ok_or(anyhow!("Invalid chunk size {chunk_size}"))?;
+                let chunk_type = reader.word()?;
+
+                let mut chunk = Reader::new(
+                    data.get(reader.pos..chunk_end)
+                        .ok_or(anyhow!("Chunk runs past the end of the file"))?,
+                );
+
+                match chunk_type {
+                    CHUNK_LAYER => {
+                        let layer_flags = chunk.word()?;
+                        let layer_type = chunk.word()?;
+                        let level = chunk.word()? as usize;
+                        chunk.skip(2 + 2)?;
+                        let blend_mode = chunk.word()?;
+                        let opacity = chunk.byte()?;
+                        chunk.skip(3)?;
+                        let name = chunk.string()?;
+
+                        groups_visible.truncate(level);
+                        let visible = layer_flags & LAYER_VISIBLE != 0
+                            && groups_visible.iter().all(|visible| *visible);
+
+                        if layer_type == LAYER_GROUP {
+                            groups_visible.push(visible);
+                        }
+
+                        file.layers.push(AsepriteLayer {
+                            name,
+                            visible: visible && blend_mode == BLEND_NORMAL,
+                            opacity: if flags & HEADER_LAYER_OPACITY != 0 {
+                                opacity
+                            } else {
+                                255
+                            },
+                        });
+                    }
+
+                    CHUNK_CEL => {
+                        let layer = chunk.word()? as usize;
+                        let x = chunk.short()? as i32;
+                        let y = chunk.short()? as i32;
+                        let opacity = chunk.byte()?;
+                        let cel_type = chunk.word()?;
+                        chunk.skip(2 + 5)?;
+
+                        let image = match cel_type {
+                            // Raw pixels
+                            0 => {
+                                let (width, height) = (chunk.word()?, chunk.word()?);
+                                file.check_cel_size(width, height)?;
+                                let size = width as usize * height as usize;
+                                let pixels = chunk.bytes(size * depth.bytes_per_pixel())?;
+
+                                Some(decode_pixels(
+                                    pixels,
+                                    width as u32,
+                                    height as u32,
+                                    depth,
+                                    &palette,
+                                    transparent_index,
+                                )?)
+                            }
+
+                            // Linked to the cel on the same layer in an earlier frame
+                            1 => {
+                                let linked = chunk.word()? as usize;
+
+                                file.frames
+                                    .get(linked)
+                                    .and_then(|frame| {
+                                        frame.cels.iter().find(|cel| cel.layer == layer)
+                                    })
+                                    .map(|cel| cel.image.clone())
+                            }
+
+                            // Zlib compressed pixels
+                            2 => {
+                                let (width, height) = (chunk.word()?, chunk.word()?);
+                                file.check_cel_size(width, height)?;
+                                let size = width as usize * height as usize;
+                                let length = size * depth.bytes_per_pixel();
+
+                                // Never inflate more than the cel needs
+                                let mut pixels = vec![];
+                                ZlibDecoder::new(&chunk.data[chunk.pos..])
+                                    .take(length as u64)
+                                    .read_to_end(&mut pixels)?;
+
+                                if pixels.len() < length {
+                                    return Err(anyhow!("Compressed cel is too small"));
+                                }
+
+                                Some(decode_pixels(
+                                    &pixels,
+                                    width as u32,
+                                    height as u32,
+                                    depth,
+                                    &palette,
+                                    transparent_index,
+                                )?)
+                            }
+
+                            // Tilemaps
+                            _ => None,
+                        };
+
+                        if let Some(image) = image {
+                            frame.cels.push(Cel {
+                                layer,
+                                x,
+                                y,
+                                opacity,
+                                image,
+                            });
+                        }
+                    }
+
+                    CHUNK_TAGS => {
+                        let count = chunk.word()?;
+                        chunk.skip(8)?;
+
+                        for _ in 0..count {
+                            let from = chunk.word()? as usize;
+                            let to = chunk.word()? as usize;
+                            let direction = match chunk.byte()? {
+                                1 => TagDirection::Reverse,
+                                2 => TagDirection::PingPong,
+                                3 => TagDirection::PingPongReverse,
+                                _ => TagDirection::Forward,
+                            };
+                            let repeat = chunk.word()?;
+                            chunk.skip(6 + 3 + 1)?;
+                            let name = chunk.string()?;
+
+                            file.tags.push(AsepriteTag {
+                                name,
+                                from,
+                                to,
+                                direction,
+                                repeat,
+                            });
+                        }
+                    }
+
+                    // The size of the palette is not needed, it never has more colors than an
+                    // index can pick
+                    CHUNK_PALETTE => {
+                        chunk.skip(4)?;
+                        let first = chunk.dword()? as usize;
+                        let last = chunk.dword()? as usize;
+                        chunk.skip(8)?;
+
+                        // Entries past the palette are not read, no index can pick them
+                        let entries = palette.iter_mut().take(last.saturating_add(1)).skip(first);
+
+                        for entry in entries {
+                            let entry_flags = chunk.word()?;
+                            let color = chunk.bytes(4)?;
+                            *entry = Rgba([color[0], color[1], color[2], color[3]]);
+
+                            if entry_flags & 1 != 0 {
+                                chunk.string()?;
+                            }
+                        }
+                    }
+
+                    // Only used by old files, newer ones also have the new palette chunk
+                    CHUNK_OLD_PALETTE => {
+                        let packets = chunk.word()?;
+                        let mut index = 0;
+
+                        for _ in 0..packets {
+                            index += chunk.byte()? as usize;
+                            let count = match chunk.byte()? {
+                                0 => 256,
+                                count => count as usize,
+                            };
+
+                            for _ in 0..count {
+                                let color = chunk.bytes(3)?;
+
+                                if let Some(entry) = palette.get_mut(index) {
+                                    *entry = Rgba([color[0], color[1], color[2], 255]);
+                                }
+
+                                index += 1;
+                            }
+                        }
+                    }
+
+                    _ => {}
+                }
+
+                reader.pos = chunk_end;
+            }
+
+            reader.pos = frame_end;
+            file.frames.push(frame);
+        }
+
+        Ok(file)
+    }
+
+    /// Cels never need to be larger than the canvas, their size comes from the file and is not
+    /// trusted
+    fn check_cel_size(&self, width: u16, height: u16) -> Result<()> {
+        if width as u32 > self.width || height as u32 > self.height {
+            return Err(anyhow!(
+                "Cel of {width}x{height} is larger than the {}x{} canvas",
+                self.width,
+                self.height
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// All visible layers of frame `index` blended together, on a transparent background
+    pub fn render_frame(&self, index: usize) -> RgbaImage {
+        let mut result = RgbaImage::new(self.width, self.height);
+
+        let Some(frame) = self.frames.get(index) else {
+            return result;
+        };
+
+        let mut cels = frame.cels.iter().collect::<Vec<_>>();
+        cels.sort_by_key(|cel| cel.layer);
+
+        for cel in cels {
+            let Some(layer) = self.layers.get(cel.layer) else {
+                continue;
+            };
+
+            if !layer.visible {
+                continue;
+            }
+
+            let opacity = cel.opacity as u32 * layer.opacity as u32 / 255;
+
+            for (x, y, pixel) in cel.image.enumerate_pixels() {
+                let (tx, ty) = (cel.x + x as i32, cel.y + y as i32);
+
+                if tx < 0 || ty < 0 || tx >= self.width as i32 || ty >= self.height as i32 {
+                    continue;
+                }
+
+                let alpha = pixel.0[3] as u32 * opacity / 255;
+                blend(result.get_pixel_mut(tx as u32, ty as u32), *pixel, alpha);
+            }
+        }
+
+        result
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&AsepriteTag> {
+        self.tags.iter().find(|tag| tag.name == name)
+    }
+}
+
+/// Puts `color` over `target` with `alpha`, like Aseprite's normal blend mode
+fn blend(target: &mut Rgba<u8>, color: Rgba<u8>, alpha: u32) {
+    if alpha == 0 {
+        return;
+    }
+
+    let below = target.0[3] as u32;
+    let out = alpha + below * (255 - alpha) / 255;
+
+    for i in 0..3 {
+        let value =
+            (color.0[i] as u32 * alpha + target.0[i] as u32 * below * (255 - alpha) / 255) / out;
+
+        target.0[i] = value as u8;
+    }
+
+    target.0[3] = out as u8;
+}
+
+fn decode_pixels(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    depth: ColorDepth,
+    palette: &[Rgba<u8>],
+    transparent_index: u8,
+) -> Result<RgbaImage> {
+    let pixels = pixels
+        .chunks_exact(depth.bytes_per_pixel())
+        .take(width as usize * height as usize)
+        .flat_map(|pixel| match depth {
+            ColorDepth::Rgba => [pixel[0], pixel[1], pixel[2], pixel[3]],
+            ColorDepth::Grayscale => [pixel[0], pixel[0], pixel[0], pixel[1]],
+            ColorDepth::Indexed if pixel[0] == transparent_index => [0, 0, 0, 0],
+            ColorDepth::Indexed => palette
+                .get(pixel[0] as usize)
+                .map_or([0, 0, 0, 0], |color| color.0),
+        })
+        .collect();
+
+    RgbaImage::from_raw(width, height, pixels).ok_or(anyhow!("Cel has the wrong size"))
+}
+
+/// Plays a tag of an Aseprite file, or all of its frames
+///
+/// Frames are drawn centered on black, sprites larger than the panel are cropped. Tags that
+/// repeat a limited number of times finish after their last repeat.
+pub struct AsepriteAnimation {
+    frames: Vec<(RgbImage, Duration)>,
+    runs: Option<u32>,
+
+    index: usize,
+    run: u32,
+
+    last_frame: Duration,
+    frame_time: Duration,
+}
+
+impl AsepriteAnimation {
+    pub fn new(file: &AsepriteFile, tag: Option<&str>) -> Result<Self> {
+        let (order, repeat) = match tag {
+            Some(name) => {
+                let tag = file
+                    .tag(name)
+                    .ok_or(anyhow!("Aseprite file has no tag {name}"))?;
+
+                (frame_order(tag), tag.repeat)
+            }
+            None => ((0..file.frames.len()).collect(), 0),
+        };
+
+        if order.is_empty() {
+            return Err(anyhow!("Aseprite animation has no frames"));
+        }
+
+        let frames = order
+            .into_iter()
+            .map(|index| {
+                let duration = file
+                    .frames
+                    .get(index)
+                    .map_or(Duration::from_millis(100), |frame| frame.duration);
+
+                (flatten(&file.render_frame(index)), duration)
+            })
+            .collect();
+
+        Ok(Self {
+            frames,
+            runs: (repeat > 0).then_some(repeat as u32),
+
+            index: 0,
+            run: 0,
+
+            last_frame: Duration::ZERO,
+            frame_time: Duration::ZERO,
+        })
+    }
+
+    pub fn open(path: impl AsRef<Path>, tag: Option<&str>) -> Result<Self> {
+        Self::new(&AsepriteFile::open(path)?, tag)
+    }
+}
+
+impl Animation for AsepriteAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        ctx.elapsed(self.last_frame) >= self.frame_time
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        if self.index == self.frames.len() {
+            self.index = 0;
+            self.run += 1;
+        }
+
+        if self.runs.is_some_and(|runs| self.run >= runs) {
+            return None;
+        }
+
+        let (image, duration) = &self.frames[self.index];
+
+        self.index += 1;
+        self.frame_time = *duration;
+        self.last_frame = ctx.now();
+
+        Some(image.clone())
+    }
+
+    fn reload(&mut self, _ctx: &mut AnimationContext) {
+        self.index = 0;
+        self.run = 0;
+    }
+}
+
+/// Frame indices of one run through `tag`
+fn frame_order(tag: &AsepriteTag) -> Vec<usize> {
+    let forward = (tag.from..=tag.to).collect::<Vec<_>>();
+    let reverse = forward.iter().rev().copied().collect::<Vec<_>>();
+
+    // Ping-pong doesn't show the frames at the ends twice
+    let bounce = |there: &[usize], back: &[usize]| {
+        let back = back.get(1..back.len().saturating_sub(1)).unwrap_or(&[]);
+
+        there.iter().chain(back).copied().collect()
+    };
+
+    match tag.direction {
+        TagDirection::Forward => forward,
+        TagDirection::Reverse => reverse,
+        TagDirection::PingPong => bounce(&forward, &reverse),
+        TagDirection::PingPongReverse => bounce(&reverse, &forward),
+    }
+}
+
+/// Draws a frame centered on the black 64x32 panel
+fn flatten(frame: &RgbaImage) -> RgbImage {
+    let mut result = RgbImage::new(64, 32);
+
+    let x = (64 - frame.width() as i64) / 2;
+    let y = (32 - frame.height() as i64) / 2;
+
+    let mut background = RgbaImage::from_pixel(64, 32, Rgba([0, 0, 0, 255]));
+    imageops::overlay(&mut background, frame, x, y);
+
+    for (pixel, source) in result.pixels_mut().zip(background.pixels()) {
+        *pixel = Rgb([source.0[0], source.0[1], source.0[2]]);
+    }
+
+    result
+}
+
+/// Lists all tags of the Aseprite files in [`SPRITES_DIR`], sorted by file name
+///
+/// Files without tags are listed once, to play all of their frames. Files that can't be read
+/// are left out.
+pub fn list_sprites() -> Vec<(PathBuf, Option<String>)> {
+    let Ok(entries) = std::fs::read_dir(SPRITES_DIR) else {
+        return vec![];
+    };
+
+    let mut files = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "ase" || ext == "aseprite")
+        })
+        .collect::<Vec<_>>();
+
+    files.sort();
+
+    files
+        .into_iter()
+        .filter_map(|path| {
+            let file = AsepriteFile::open(&path).ok()?;
+
+            if file.tags.is_empty() {
+                return Some(vec![(path, None)]);
+            }
+
+            Some(
+                file.tags
+                    .into_iter()
+                    .map(|tag| (path.clone(), Some(tag.name)))
+                    .collect(),
+            )
+        })
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Offsets of the chunks of `chunk_type` in the first frame of an Aseprite file
+    fn aseprite_chunks(data: &[u8], chunk_type: u16) -> Vec<usize> {
+        let word = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]);
+        let dword = |pos: usize| u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+
+        let mut pos = 128 + 16;
+        let mut chunks = vec![];
+
+        for _ in 0..word(128 + 6) {
+            if word(pos + 4) == chunk_type {
+                chunks.push(pos);
+            }
+
+            pos += dword(pos) as usize;
+        }
+
+        chunks
+    }
+
+    #[test]
+    fn aseprite_untrusted() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("sprites/heart.aseprite");
+        let data = fs::read(path).unwrap();
+
+        // Layers with another blend mode than normal are not drawn
+        let mut multiply = data.clone();
+        let heart = aseprite_chunks(&multiply, CHUNK_LAYER)[2];
+        multiply[heart + 16] = 1;
+
+        let file = AsepriteFile::parse(&multiply).unwrap();
+        assert!(!file.layers[2].visible);
+
+        // A compressed cel that claims to be larger than the canvas is rejected before inflating
+        let mut huge = data.clone();
+        let cel = aseprite_chunks(&huge, CHUNK_CEL)[2];
+        huge[cel + 22..cel + 26].copy_from_slice(&[0xff; 4]);
+
+        assert!(AsepriteFile::parse(&huge).is_err());
+
+        // So is a canvas that is larger than any sprite needs
+        let mut canvas = data.clone();
+        canvas[8..12].copy_from_slice(&[0xff; 4]);
+
+        assert!(AsepriteFile::parse(&canvas).is_err());
+
+        // A palette that claims more colors than an index can pick doesn't grow past them
+        let mut palette = data.clone();
+        let chunk = aseprite_chunks(&palette, CHUNK_PALETTE)[0];
+        palette[chunk + 6..chunk + 10].copy_from_slice(&[0xff; 4]);
+
+        let file = AsepriteFile::parse(&palette).unwrap();
+        assert!(file.render_frame(0) == AsepriteFile::parse(&data).unwrap().render_frame(0));
+
+        // A frame that runs past the end of the file is an error
+        let mut frame = data.clone();
+        frame[128..132].copy_from_slice(&[0xff; 4]);
+
+        assert!(AsepriteFile::parse(&frame).is_err());
+    }
+}
@@ -1,66 +1,66 @@
-use std::time::Instant;
+use std::time::Duration;
 
 use image::{
     imageops::{resize, FilterType},
     Rgb, RgbImage,
 };
-use rand::Rng;
 
-use crate::image::hsl_to_rgb;
+use crate::matrix::color_utils::Palette;
 
-use super::Animation;
+use super::{Animation, AnimationContext};
 
 // Arbritrarily increase size of panel, so that specific segment gaps can be achieved
 const BUFFER_HEIGHT: u32 = 18;
 
 pub struct BlocksAnimation {
     frame: u32,
-    last_frame: Instant,
+    last_frame: Duration,
 
+    palette: Palette,
     segments: Vec<(u8, Rgb<u8>)>,
 }
 
 impl BlocksAnimation {
-    pub fn new() -> Self {
-        let mut rng = rand::thread_rng();
-        let mut segments = vec![];
-
-        for i in 0..6 {
-            let color = hsl_to_rgb(rng.gen_range(0..360), 1.0, 0.5);
-
-            segments.push((i * 3, color));
-        }
-
+    pub fn new(palette: Palette) -> Self {
         Self {
             frame: 0,
-            last_frame: Instant::now(),
+            last_frame: Duration::ZERO,
+
+            palette,
 
-            segments,
+            // Colors are picked on the first frame, using the context's randomness
+            segments: vec![],
         }
     }
 }
 
 impl Default for BlocksAnimation {
     fn default() -> Self {
-        Self::new()
+        Self::new(Palette::default())
     }
 }
 
 impl Animation for BlocksAnimation {
-    fn should_execute(&self) -> bool {
-        self.last_frame.elapsed().as_millis() > 20
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        ctx.elapsed(self.last_frame).as_millis() > 20
     }
 
-    fn next_frame(&mut self) -> Option<RgbImage> {
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
         let mut image = RgbImage::new(32, 16);
 
+        if self.segments.is_empty() {
+            for i in 0..6 {
+                let color = self.palette.pick(&mut ctx.rng);
+
+                self.segments.push((i * 3, color));
+            }
+        }
+
         if self.frame % 20 == 0 {
             self.segments.iter_mut().for_each(|segment| {
-                let mut rng = rand::thread_rng();
-
                 if segment.0 == 0 {
                     segment.0 = (BUFFER_HEIGHT - 1) as u8;
-                    segment.1 = hsl_to_rgb(rng.gen_range(0..360), 1.0, 0.5);
+                    segment.1 = self.palette.pick(&mut ctx.rng);
                 } else {
                     segment.0 -= 1;
                 }
@@ -91,7 +91,7 @@ impl Animation for BlocksAnimation {
                     set_pixel(
                         &mut image,
                         x,
-                        (*pos as u32 - 1 + BUFFER_HEIGHT) % BUFFER_HEIGHT,
+                        (*pos as u32 + BUFFER_HEIGHT - 1) % BUFFER_HEIGHT,
                         color,
                     );
                 }
@@ -100,14 +100,14 @@ impl Animation for BlocksAnimation {
                     set_pixel(
                         &mut image,
                         x,
-                        (*pos as u32 - 1 + BUFFER_HEIGHT) % BUFFER_HEIGHT,
+                        (*pos as u32 + BUFFER_HEIGHT - 1) % BUFFER_HEIGHT,
                         color2,
                     );
                 } else {
                     set_pixel(
                         &mut image,
                         x,
-                        (*pos as u32 - 2 + BUFFER_HEIGHT) % BUFFER_HEIGHT,
+                        (*pos as u32 + BUFFER_HEIGHT - 2) % BUFFER_HEIGHT,
                         color2,
                     );
                 }
@@ -117,7 +117,7 @@ impl Animation for BlocksAnimation {
         let resized = resize(&image, 64, 32, FilterType::Nearest);
 
         self.frame = (self.frame + 1) % 20;
-        self.last_frame = Instant::now();
+        self.last_frame = ctx.now();
 
         Some(resized)
     }
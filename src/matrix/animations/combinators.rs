@@ -0,0 +1,329 @@
+use std::time::Duration;
+
+use image::{imageops, Pixel, Rgb, RgbImage};
+
+use super::{Animation, AnimationContext};
+
+/// An animation together with the last frame it produced
+///
+/// Combinators need a frame of every child on each of their own frames, while the children
+/// all run at their own frame rate.
+struct Layer {
+    animation: Box<dyn Animation + Send + Sync>,
+    frame: Option<RgbImage>,
+}
+
+impl Layer {
+    fn new(animation: Box<dyn Animation + Send + Sync>) -> Self {
+        Self {
+            animation,
+            frame: None,
+        }
+    }
+
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        self.frame.is_none() || self.animation.should_execute(ctx)
+    }
+
+    /// Renders a new frame if the animation wants one, returns [`None`] once it has finished
+    fn update(&mut self, ctx: &mut AnimationContext) -> Option<&RgbImage> {
+        if self.should_execute(ctx) {
+            self.frame = Some(self.animation.next_frame(ctx)?);
+        }
+
+        self.frame.as_ref()
+    }
+
+    fn reload(&mut self, ctx: &mut AnimationContext) {
+        self.animation.reload(ctx);
+    }
+}
+
+/// Draws `top` over `base`, pixels of `top` that have the key color are left transparent
+///
+/// Finishes as soon as either of the animations finishes.
+pub struct OverlayAnimation {
+    base: Layer,
+    top: Layer,
+    key: Rgb<u8>,
+}
+
+impl OverlayAnimation {
+    pub fn new(
+        base: Box<dyn Animation + Send + Sync>,
+        top: Box<dyn Animation + Send + Sync>,
+        key: Rgb<u8>,
+    ) -> Self {
+        Self {
+            base: Layer::new(base),
+            top: Layer::new(top),
+            key,
+        }
+    }
+}
+
+impl Animation for OverlayAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        self.base.should_execute(ctx) || self.top.should_execute(ctx)
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        let mut result = self.base.update(ctx)?.clone();
+        let top = self.top.update(ctx)?;
+
+        for (pixel, top) in result.pixels_mut().zip(top.pixels()) {
+            if *top != self.key {
+                *pixel = *top;
+            }
+        }
+
+        Some(result)
+    }
+
+    fn reload(&mut self, ctx: &mut AnimationContext) {
+        self.base.reload(ctx);
+        self.top.reload(ctx);
+    }
+}
+
+/// Shows `source` only where `mask` is lit, dimmed by the brightness of the mask
+///
+/// Finishes as soon as either of the animations finishes.
+pub struct MaskAnimation {
+    source: Layer,
+    mask: Layer,
+}
+
+impl MaskAnimation {
+    pub fn new(
+        source: Box<dyn Animation + Send + Sync>,
+        mask: Box<dyn Animation + Send + Sync>,
+    ) -> Self {
+        Self {
+            source: Layer::new(source),
+            mask: Layer::new(mask),
+        }
+    }
+}
+
+impl Animation for MaskAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        self.source.should_execute(ctx) || self.mask.should_execute(ctx)
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        let mut result = self.source.update(ctx)?.clone();
+        let mask = self.mask.update(ctx)?;
+
+        for (pixel, mask) in result.pixels_mut().zip(mask.pixels()) {
+            let luma = mask.to_luma().0[0] as u16;
+
+            pixel.apply(|c| (c as u16 * luma / 255) as u8);
+        }
+
+        Some(result)
+    }
+
+    fn reload(&mut self, ctx: &mut AnimationContext) {
+        self.source.reload(ctx);
+        self.mask.reload(ctx);
+    }
+}
+
+/// Puts two animations side by side, each getting a 32x32 half of the panel
+///
+/// Frames that are already 32 pixels wide are used as is, full size frames are cropped to
+/// their center. Finishes as soon as either of the animations finishes.
+pub struct SplitScreenAnimation {
+    left: Layer,
+    right: Layer,
+}
+
+impl SplitScreenAnimation {
+    pub fn new(
+        left: Box<dyn Animation + Send + Sync>,
+        right: Box<dyn Animation + Send + Sync>,
+    ) -> Self {
+        Self {
+            left: Layer::new(left),
+            right: Layer::new(right),
+        }
+    }
+}
+
+impl Animation for SplitScreenAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        self.left.should_execute(ctx) || self.right.should_execute(ctx)
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        let mut result = RgbImage::new(64, 32);
+
+        let left = half(self.left.update(ctx)?);
+        imageops::replace(&mut result, &left, 0, 0);
+
+        let right = half(self.right.update(ctx)?);
+        imageops::replace(&mut result, &right, 32, 0);
+
+        Some(result)
+    }
+
+    fn reload(&mut self, ctx: &mut AnimationContext) {
+        self.left.reload(ctx);
+        self.right.reload(ctx);
+    }
+}
+
+fn half(frame: &RgbImage) -> RgbImage {
+    let x = frame.width().saturating_sub(32) / 2;
+
+    imageops::crop_imm(frame, x, 0, 32, 32).to_image()
+}
+
+/// Stops an animation after it has been running for `limit`
+pub struct TimeLimitAnimation {
+    animation: Box<dyn Animation + Send + Sync>,
+    limit: Duration,
+
+    start: Option<Duration>,
+}
+
+impl TimeLimitAnimation {
+    pub fn new(animation: Box<dyn Animation + Send + Sync>, limit: Duration) -> Self {
+        Self {
+            animation,
+            limit,
+
+            start: None,
+        }
+    }
+
+    fn expired(&self, ctx: &AnimationContext) -> bool {
+        self.start
+            .is_some_and(|start| ctx.elapsed(start) >= self.limit)
+    }
+}
+
+impl Animation for TimeLimitAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        // Let the scheduler pick up that we're done
+        self.expired(ctx) || self.animation.should_execute(ctx)
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        if self.expired(ctx) {
+            return None;
+        }
+
+        self.start.get_or_insert(ctx.now());
+        self.animation.next_frame(ctx)
+    }
+
+    fn reload(&mut self, ctx: &mut AnimationContext) {
+        self.start = None;
+        self.animation.reload(ctx);
+    }
+}
+
+/// Plays an animation `times` times in a row, creating a fresh one for every run
+///
+/// Only makes sense for animations that finish, like one wrapped in a [`TimeLimitAnimation`].
+pub struct RepeatAnimation {
+    factory: Box<dyn Fn() -> Box<dyn Animation + Send + Sync> + Send + Sync>,
+    animation: Box<dyn Animation + Send + Sync>,
+
+    times: u32,
+    run: u32,
+}
+
+impl RepeatAnimation {
+    pub fn new(
+        times: u32,
+        factory: impl Fn() -> Box<dyn Animation + Send + Sync> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            animation: factory(),
+            factory: Box::new(factory),
+
+            times,
+            run: 0,
+        }
+    }
+}
+
+impl Animation for RepeatAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        self.run >= self.times || self.animation.should_execute(ctx)
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        while self.run < self.times {
+            if let Some(frame) = self.animation.next_frame(ctx) {
+                return Some(frame);
+            }
+
+            self.run += 1;
+            self.animation = (self.factory)();
+        }
+
+        None
+    }
+
+    fn reload(&mut self, ctx: &mut AnimationContext) {
+        self.run = 0;
+        self.animation = (self.factory)();
+        self.animation.reload(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::OffsetDateTime;
+
+    use super::*;
+    use crate::matrix::animations::{step_animation, EyesAnimation, FakeClock, StartupAnimation};
+
+    const TICK: Duration = Duration::from_millis(5);
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn time_limit() {
+        let clock = FakeClock::new(OffsetDateTime::UNIX_EPOCH);
+        let mut ctx = AnimationContext::with_clock(clock.clone(), 0);
+
+        let mut animation =
+            TimeLimitAnimation::new(Box::new(EyesAnimation::new()), Duration::from_secs(1));
+
+        let mut frames = 0;
+        while step_animation(&mut animation, &mut ctx, &clock, TICK, TIMEOUT).is_some() {
+            frames += 1;
+        }
+
+        assert!(frames > 0);
+        assert!(ctx.now() >= Duration::from_secs(1) && ctx.now() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn repeat() {
+        let clock = FakeClock::new(OffsetDateTime::UNIX_EPOCH);
+        let mut ctx = AnimationContext::with_clock(clock.clone(), 0);
+
+        let mut animation = RepeatAnimation::new(3, || {
+            let startup = StartupAnimation::load().unwrap();
+
+            Box::new(TimeLimitAnimation::new(
+                Box::new(startup),
+                Duration::from_secs(1),
+            ))
+        });
+
+        let mut frames = 0;
+        while step_animation(&mut animation, &mut ctx, &clock, TICK, TIMEOUT).is_some() {
+            frames += 1;
+        }
+
+        // Ferris is revealed in 750ms, which is 26 frames at the test tick, then the limit is awaited
+        assert_eq!(frames, 3 * 26);
+        assert!(ctx.now() >= Duration::from_secs(3) && ctx.now() < Duration::from_secs(4));
+    }
+}
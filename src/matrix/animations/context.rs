@@ -0,0 +1,150 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use image::RgbImage;
+use rand::{rngs::StdRng, SeedableRng};
+use time::OffsetDateTime;
+
+use super::Animation;
+
+/// Source of time for animations
+pub trait Clock {
+    /// Monotonic time since the clock was created
+    fn now(&self) -> Duration;
+
+    /// Current UTC date and time, for animations that show the actual time
+    fn wall_time(&self) -> OffsetDateTime;
+}
+
+/// The clock used on the actual panel
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn wall_time(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
+
+/// A clock that only moves when it is told to
+///
+/// Clones share the same time, so one can be handed to an [`AnimationContext`] while the other
+/// is used to advance it.
+#[derive(Clone)]
+pub struct FakeClock {
+    elapsed: Arc<Mutex<Duration>>,
+    wall_start: OffsetDateTime,
+}
+
+impl FakeClock {
+    /// Starts a clock at zero, with the wall time starting at `wall_start`
+    pub fn new(wall_start: OffsetDateTime) -> Self {
+        Self {
+            elapsed: Arc::new(Mutex::new(Duration::ZERO)),
+            wall_start,
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().expect("clock lock poisoned") += duration;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Duration {
+        *self.elapsed.lock().expect("clock lock poisoned")
+    }
+
+    fn wall_time(&self) -> OffsetDateTime {
+        self.wall_start + self.now()
+    }
+}
+
+/// Everything an animation needs from the outside world
+///
+/// Animations take their time and randomness from here instead of [`Instant::now`] and
+/// [`rand::thread_rng`], so that a fake clock and fixed seed reproduce the exact same frames.
+pub struct AnimationContext {
+    clock: Box<dyn Clock + Send + Sync>,
+    pub rng: StdRng,
+}
+
+impl AnimationContext {
+    /// Real time and an unpredictable seed
+    pub fn new() -> Self {
+        Self {
+            clock: Box::new(SystemClock::new()),
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    pub fn with_clock(clock: impl Clock + Send + Sync + 'static, seed: u64) -> Self {
+        Self {
+            clock: Box::new(clock),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn now(&self) -> Duration {
+        self.clock.now()
+    }
+
+    pub fn wall_time(&self) -> OffsetDateTime {
+        self.clock.wall_time()
+    }
+
+    /// Time that has passed since `since`, which was taken from [`Self::now`]
+    pub fn elapsed(&self, since: Duration) -> Duration {
+        self.now().saturating_sub(since)
+    }
+}
+
+impl Default for AnimationContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Advances `clock` in `tick` steps until `animation` wants a new frame, then renders that frame
+///
+/// Returns [`None`] when the animation has finished, or hasn't asked for a frame within `timeout`
+pub fn step_animation(
+    animation: &mut dyn Animation,
+    ctx: &mut AnimationContext,
+    clock: &FakeClock,
+    tick: Duration,
+    timeout: Duration,
+) -> Option<RgbImage> {
+    let start = ctx.now();
+
+    while !animation.should_execute(ctx) {
+        if ctx.elapsed(start) > timeout {
+            return None;
+        }
+
+        clock.advance(tick);
+    }
+
+    animation.next_frame(ctx)
+}
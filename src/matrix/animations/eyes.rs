@@ -1,40 +1,302 @@
-use std::time::Instant;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use image::RgbImage;
 use rand::Rng;
 
-use super::Animation;
+use super::{Animation, AnimationContext, SpriteSheet};
 
-const BITMAP_SIZE: usize = 64 * 32 * 3;
-const EYES_IMAGE_DATA: &[u8] = include_bytes!("../../../images/eyes.raw");
+const EYES_MANIFEST: &str = include_str!("../../../images/eyes.toml");
+const EYES_SHEET: &[u8] = include_bytes!("../../../images/eyes.png");
 
-// 0: eyes_closed
-// 1: eyes_default
-// 2: eyes_look_apart
-// 3: eyes_look_crossed
-// 4: eyes_look_left
-// 5: eyes_look_right
+// The mood stays neutral for the first 30 seconds, after which the idle personality kicks in
+const IDLE_START_FRAMES: u32 = 300;
+
+// How long expressions and gazes picked on the deck are held, at 10 frames per second
+const EXPRESSION_FRAMES: u32 = 50;
+const GAZE_FRAMES: u32 = 30;
+
+/// How the eyes feel, every mood has its own row of frames in the eyes sprite sheet
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mood {
+    Neutral,
+    Happy,
+    Angry,
+    Sleepy,
+    Surprised,
+    Love,
+}
+
+impl Mood {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Neutral => "Neutral",
+            Self::Happy => "Happy",
+            Self::Angry => "Angry",
+            Self::Sleepy => "Sleepy",
+            Self::Surprised => "Wow",
+            Self::Love => "Love",
+        }
+    }
+
+    /// Frames until the next blink
+    fn blink_interval<R: Rng>(&self, rng: &mut R) -> u32 {
+        match self {
+            Self::Neutral => rng.gen_range(30..75),
+            Self::Happy => rng.gen_range(30..60),
+            Self::Angry => rng.gen_range(60..120),
+            Self::Sleepy => rng.gen_range(8..25),
+            Self::Surprised => rng.gen_range(80..150),
+            Self::Love => rng.gen_range(15..40),
+        }
+    }
+
+    /// Frames the eyes stay closed for a blink
+    fn blink_length<R: Rng>(&self, rng: &mut R) -> u32 {
+        match self {
+            // Heavy eyelids
+            Self::Sleepy => rng.gen_range(2..=5),
+            Self::Love => rng.gen_range(1..=2),
+            _ => 1,
+        }
+    }
+
+    /// Frames the idle personality stays in this mood
+    fn duration<R: Rng>(&self, rng: &mut R) -> u32 {
+        match self {
+            Self::Neutral => rng.gen_range(150..400),
+            Self::Sleepy => rng.gen_range(80..200),
+            _ => rng.gen_range(40..120),
+        }
+    }
+
+    /// The mood the idle personality drifts to after this one
+    fn drift<R: Rng>(&self, rng: &mut R) -> Self {
+        let roll = rng.gen_range(0..100);
+
+        match self {
+            Self::Neutral => match roll {
+                0..=29 => Self::Happy,
+                30..=49 => Self::Sleepy,
+                50..=64 => Self::Surprised,
+                65..=79 => Self::Love,
+                80..=89 => Self::Angry,
+                _ => Self::Neutral,
+            },
+            Self::Happy => match roll {
+                0..=49 => Self::Neutral,
+                50..=79 => Self::Love,
+                _ => Self::Surprised,
+            },
+            Self::Angry => match roll {
+                0..=79 => Self::Neutral,
+                _ => Self::Surprised,
+            },
+            // Sometimes wakes up with a start
+            Self::Sleepy => match roll {
+                0..=39 => Self::Neutral,
+                40..=69 => Self::Sleepy,
+                _ => Self::Surprised,
+            },
+            Self::Surprised => match roll {
+                0..=49 => Self::Neutral,
+                50..=79 => Self::Happy,
+                _ => Self::Angry,
+            },
+            Self::Love => match roll {
+                0..=49 => Self::Happy,
+                _ => Self::Neutral,
+            },
+        }
+    }
+
+    /// The frame of the eyes sprite sheet that shows the mood, for the eyes looking at `look`
+    /// or `closed`
+    ///
+    /// Neutral frames are only named after where they look, the others start with the mood.
+    fn frame_name(&self, look: &str) -> String {
+        let mood = match self {
+            Self::Neutral => return look.to_string(),
+            Self::Happy => "happy",
+            Self::Angry => "angry",
+            Self::Sleepy => "sleepy",
+            Self::Surprised => "surprised",
+            Self::Love => "love",
+        };
+
+        format!("{mood}_{look}")
+    }
+}
+
+/// Where the eyes look, each one is a column of frames in the eyes sprite sheet
+#[derive(Clone, Copy, PartialEq)]
+pub enum Gaze {
+    Center,
+    Left,
+    Right,
+    Apart,
+    Crossed,
+}
+
+impl Gaze {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Center => "Center",
+            Self::Left => "Left",
+            Self::Right => "Right",
+            Self::Apart => "Apart",
+            Self::Crossed => "Cross",
+        }
+    }
+
+    fn random<R: Rng>(rng: &mut R) -> Self {
+        match rng.gen_range(0..=100u32) {
+            0..=30 => Self::Center,
+            31..=60 => Self::Left,
+            61..=90 => Self::Right,
+            91..=95 => Self::Apart,
+            96.. => Self::Crossed,
+        }
+    }
+
+    fn frame_name(&self) -> &'static str {
+        match self {
+            Self::Center => "center",
+            Self::Apart => "apart",
+            Self::Crossed => "crossed",
+            Self::Left => "left",
+            Self::Right => "right",
+        }
+    }
+}
+
+struct EyesState {
+    idle: bool,
+
+    mood: Option<Mood>,
+    gaze: Option<Gaze>,
+}
+
+/// Shared controls for the moods and gaze of the eyes
+///
+/// The animation lives on the matrix thread, so the deck keeps a clone of this to control it.
+/// Requests are picked up on the next frame of the animation.
+#[derive(Clone)]
+pub struct EyesControl(Arc<Mutex<EyesState>>);
+
+impl EyesControl {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(EyesState {
+            idle: true,
+
+            mood: None,
+            gaze: None,
+        })))
+    }
+
+    /// Shows `mood` for a few seconds, or until another one is picked if idling is off
+    pub fn express(&self, mood: Mood) {
+        self.0.lock().expect("eyes lock poisoned").mood = Some(mood);
+    }
+
+    /// Looks towards `gaze` for a few seconds
+    pub fn look(&self, gaze: Gaze) {
+        self.0.lock().expect("eyes lock poisoned").gaze = Some(gaze);
+    }
+
+    /// Lets the eyes drift between moods by themselves
+    pub fn set_idle(&self, idle: bool) {
+        self.0.lock().expect("eyes lock poisoned").idle = idle;
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.0.lock().expect("eyes lock poisoned").idle
+    }
+}
+
+impl Default for EyesControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 pub struct EyesAnimation {
+    sheet: SpriteSheet,
+    frame_time: Duration,
+
+    control: EyesControl,
+
+    mood: Mood,
+    mood_frames: u32,
+
+    /// [`None`] right after a blink, until a new gaze is picked
+    gaze: Option<Gaze>,
+    gaze_frames: u32,
+
     frame: u32,
     eyes_closed_frames: u32,
     next_eyes_frame: u32,
 
-    last_frame: Instant,
-
-    current_eye: u8,
+    last_frame: Duration,
 }
 
 impl EyesAnimation {
     pub fn new() -> Self {
+        Self::with_control(EyesControl::new())
+    }
+
+    pub fn with_control(control: EyesControl) -> Self {
+        let sheet = SpriteSheet::parse(EYES_MANIFEST, EYES_SHEET)
+            .expect("Eyes sprite sheet is embedded in the binary");
+
         Self {
+            frame_time: sheet
+                .frame("center")
+                .map_or(Duration::from_millis(100), |f| f.duration),
+            sheet,
+
+            control,
+
+            mood: Mood::Neutral,
+            mood_frames: IDLE_START_FRAMES,
+
+            gaze: Some(Gaze::Center),
+            gaze_frames: 0,
+
             frame: 0,
             eyes_closed_frames: 0,
-            next_eyes_frame: rand::thread_rng().gen_range(30..75),
+            // Random from the first blink onwards
+            next_eyes_frame: 50,
 
-            last_frame: Instant::now(),
+            last_frame: Duration::ZERO,
+        }
+    }
+
+    fn update_mood<R: Rng>(&mut self, rng: &mut R) {
+        let (idle, mood, gaze) = {
+            let mut state = self.control.0.lock().expect("eyes lock poisoned");
+
+            (state.idle, state.mood.take(), state.gaze.take())
+        };
+
+        if let Some(mood) = mood {
+            self.mood = mood;
+            self.mood_frames = EXPRESSION_FRAMES;
+        } else if idle {
+            if self.mood_frames == 0 {
+                self.mood = self.mood.drift(rng);
+                self.mood_frames = self.mood.duration(rng);
+            }
 
-            current_eye: 1,
+            self.mood_frames -= 1;
+        }
+
+        if let Some(gaze) = gaze {
+            self.gaze = Some(gaze);
+            self.gaze_frames = GAZE_FRAMES;
+        } else {
+            self.gaze_frames = self.gaze_frames.saturating_sub(1);
         }
     }
 }
@@ -46,57 +308,38 @@ impl Default for EyesAnimation {
 }
 
 impl Animation for EyesAnimation {
-    fn should_execute(&self) -> bool {
-        // Target 10 FPS
-        self.last_frame.elapsed().as_millis() > 100
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        ctx.elapsed(self.last_frame) > self.frame_time
     }
 
-    fn next_frame(&mut self) -> Option<image::RgbImage> {
-        let mut rng = rand::thread_rng();
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<image::RgbImage> {
+        self.update_mood(&mut ctx.rng);
 
         if self.frame > self.next_eyes_frame {
             self.frame = 0;
-            self.eyes_closed_frames = 1;
-            self.current_eye = 0;
-            self.next_eyes_frame = rng.gen_range(30..75);
+            self.eyes_closed_frames = self.mood.blink_length(&mut ctx.rng);
+            self.next_eyes_frame = self.mood.blink_interval(&mut ctx.rng);
+
+            // Keep looking where the deck asked to
+            if self.gaze_frames == 0 {
+                self.gaze = None;
+            }
         }
 
         if self.eyes_closed_frames > 0 {
             self.eyes_closed_frames -= 1;
-            self.last_frame = Instant::now();
+            self.last_frame = ctx.now();
 
-            return Some(get_image_from_index(0));
+            return Some(self.sheet.image(&self.mood.frame_name("closed")).clone());
         }
 
-        if self.current_eye == 0 {
-            // Pick random eye
+        let gaze = *self.gaze.get_or_insert_with(|| Gaze::random(&mut ctx.rng));
 
-            let number = rng.gen_range(0..=100u32);
-            self.current_eye = match number {
-                0..=30 => 1,
-                31..=60 => 4,
-                61..=90 => 5,
-                91..=95 => 2,
-                96.. => 3,
-            }
-        }
+        let image = self.sheet.image(&self.mood.frame_name(gaze.frame_name()));
 
         self.frame += 1;
-        self.last_frame = Instant::now();
+        self.last_frame = ctx.now();
 
-        Some(get_image_from_index(self.current_eye as usize))
+        Some(image.clone())
     }
 }
-
-fn get_image_from_index(idx: usize) -> RgbImage {
-    if idx > 5 {
-        panic!("Index out of range 0-5");
-    }
-
-    RgbImage::from_raw(
-        64,
-        32,
-        EYES_IMAGE_DATA[BITMAP_SIZE * idx..BITMAP_SIZE * (idx + 1)].to_vec(),
-    )
-    .expect("Buffer has invalid length")
-}
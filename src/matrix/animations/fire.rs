@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::RgbColor};
+use image::{Rgb, RgbImage};
+use rand::Rng;
+
+use crate::matrix::color_utils::{interpolate_colors, Palette};
+
+use super::{Animation, AnimationContext};
+
+const WIDTH: usize = 64;
+
+// One extra row at the bottom that is always burning at full heat, but is never shown
+const HEIGHT: usize = 33;
+
+const MAX_HEAT: u8 = 36;
+
+// Black, red, orange, yellow and white, like the fire in the Doom PSX intro
+const CLASSIC: &[Rgb888] = &[
+    Rgb888::new(7, 7, 7),
+    Rgb888::new(143, 39, 7),
+    Rgb888::new(223, 87, 7),
+    Rgb888::new(207, 127, 15),
+    Rgb888::new(191, 167, 39),
+    Rgb888::new(255, 255, 255),
+];
+
+/// Doom style fire, heat rises from the bottom row and randomly cools and drifts sideways
+///
+/// Random palettes burn in the classic fire colors, other palettes from black through their own
+/// colors to white.
+pub struct FireAnimation {
+    colors: Vec<Rgb<u8>>,
+    frame_time: Duration,
+
+    heat: Vec<u8>,
+    last_frame: Duration,
+}
+
+impl FireAnimation {
+    pub fn new(palette: Palette, speed: f32) -> Self {
+        let mut heat = vec![0; WIDTH * HEIGHT];
+        heat[WIDTH * (HEIGHT - 1)..].fill(MAX_HEAT);
+
+        Self {
+            colors: heat_colors(&palette),
+            frame_time: Duration::from_secs_f32(0.04 / speed.max(0.01)),
+
+            heat,
+            last_frame: Duration::ZERO,
+        }
+    }
+
+    fn spread<R: Rng>(&mut self, rng: &mut R) {
+        for y in 1..HEIGHT {
+            for x in 0..WIDTH {
+                let src = y * WIDTH + x;
+                let heat = self.heat[src];
+
+                if heat == 0 {
+                    self.heat[src - WIDTH] = 0;
+                    continue;
+                }
+
+                // The panel is a lot lower than the original fire, so it has to cool down faster
+                let drift = rng.gen_range(0..3);
+                let cooling = rng.gen_range(0..4);
+                let dst = (src - WIDTH + 1).saturating_sub(drift);
+
+                self.heat[dst] = heat.saturating_sub(cooling);
+            }
+        }
+    }
+}
+
+impl Animation for FireAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        ctx.elapsed(self.last_frame) > self.frame_time
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        self.spread(&mut ctx.rng);
+
+        let mut image = RgbImage::new(64, 32);
+
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let heat = self.heat[y as usize * WIDTH + x as usize];
+
+            *pixel = self.colors[heat as usize];
+        }
+
+        self.last_frame = ctx.now();
+
+        Some(image)
+    }
+}
+
+fn heat_colors(palette: &Palette) -> Vec<Rgb<u8>> {
+    let colors = match palette {
+        Palette::Random(_) => CLASSIC.to_vec(),
+        _ => [Rgb888::BLACK]
+            .into_iter()
+            .chain(palette.colors())
+            .chain([Rgb888::WHITE])
+            .collect(),
+    };
+
+    (0..=MAX_HEAT)
+        .map(|heat| {
+            // Keep the coldest heat completely black
+            if heat == 0 {
+                return Rgb([0, 0, 0]);
+            }
+
+            let color = interpolate_colors(&colors, heat as f32 / MAX_HEAT as f32);
+
+            Rgb([color.r(), color.g(), color.b()])
+        })
+        .collect()
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::RgbColor};
+use image::{Rgb, RgbImage};
+use rand::Rng;
+
+use crate::matrix::color_utils::{self, Palette};
+
+use super::{Animation, AnimationContext};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 32;
+
+// Chance for a cell to be alive when seeding
+const SEED_DENSITY: f64 = 0.35;
+
+// Repeating one of the last generations means the board is stuck in a still life or oscillator
+const HISTORY: usize = 16;
+
+// Generations a stagnated board is still shown before reseeding
+const STAGNANT_GENERATIONS: u32 = 30;
+
+/// Conway's Game of Life on a wrapping board, which is reseeded once it stagnates
+///
+/// Cells are colored by their age, running through the palette.
+pub struct LifeAnimation {
+    table: Vec<Rgb888>,
+    palette: Palette,
+    frame_time: Duration,
+
+    // Age of every cell in generations, 0 is dead
+    cells: Vec<u16>,
+    history: VecDeque<u64>,
+    stagnant: u32,
+
+    last_frame: Duration,
+}
+
+impl LifeAnimation {
+    pub fn new(palette: Palette, speed: f32) -> Self {
+        Self {
+            table: color_utils::generate_table(&palette),
+            palette,
+            frame_time: Duration::from_secs_f32(0.1 / speed.max(0.01)),
+
+            // Seeded on the first frame, using the context's randomness
+            cells: vec![],
+            history: VecDeque::new(),
+            stagnant: 0,
+
+            last_frame: Duration::ZERO,
+        }
+    }
+
+    fn seed<R: Rng>(&mut self, rng: &mut R) {
+        self.cells = (0..WIDTH * HEIGHT)
+            .map(|_| rng.gen_bool(SEED_DENSITY) as u16)
+            .collect();
+
+        self.history.clear();
+        self.stagnant = 0;
+    }
+
+    fn step(&mut self) {
+        let alive = |x: usize, y: usize| self.cells[y * WIDTH + x] > 0;
+
+        let next = (0..WIDTH * HEIGHT)
+            .map(|i| {
+                let (x, y) = (i % WIDTH, i / WIDTH);
+
+                let mut neighbours = 0;
+                for (dx, dy) in [
+                    (0, 0),
+                    (1, 0),
+                    (2, 0),
+                    (0, 1),
+                    (2, 1),
+                    (0, 2),
+                    (1, 2),
+                    (2, 2),
+                ] {
+                    let nx = (x + WIDTH + dx - 1) % WIDTH;
+                    let ny = (y + HEIGHT + dy - 1) % HEIGHT;
+
+                    neighbours += alive(nx, ny) as u8;
+                }
+
+                match (alive(x, y), neighbours) {
+                    (true, 2 | 3) => self.cells[i].saturating_add(1),
+                    (false, 3) => 1,
+                    _ => 0,
+                }
+            })
+            .collect();
+
+        self.cells = next;
+    }
+
+    /// Keeps track of recent generations, returns whether the board has stopped changing
+    fn check_stagnation(&mut self) -> bool {
+        let mut hasher = DefaultHasher::new();
+        self.cells
+            .iter()
+            .map(|age| *age > 0)
+            .collect::<Vec<_>>()
+            .hash(&mut hasher);
+        let hash = hasher.finish();
+
+        if self.history.contains(&hash) {
+            self.stagnant += 1;
+        } else {
+            self.stagnant = 0;
+        }
+
+        self.history.push_back(hash);
+        if self.history.len() > HISTORY {
+            self.history.pop_front();
+        }
+
+        self.stagnant >= STAGNANT_GENERATIONS
+    }
+}
+
+impl Animation for LifeAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        ctx.elapsed(self.last_frame) > self.frame_time
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        if self.cells.is_empty() || self.check_stagnation() {
+            self.seed(&mut ctx.rng);
+        } else {
+            self.step();
+        }
+
+        let mut image = RgbImage::new(64, 32);
+
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let age = self.cells[y as usize * WIDTH + x as usize];
+
+            if age > 0 {
+                let color = self.table[(age as usize - 1) % self.table.len()];
+                *pixel = Rgb([color.r(), color.g(), color.b()]);
+            }
+        }
+
+        self.last_frame = ctx.now();
+
+        Some(image)
+    }
+
+    fn reload(&mut self, ctx: &mut AnimationContext) {
+        self.palette.reseed(&mut ctx.rng);
+        self.table = color_utils::generate_table(&self.palette);
+    }
+}
@@ -1,23 +1,40 @@
+mod aseprite;
 mod blocks;
+mod combinators;
+mod context;
 mod eyes;
 mod falling;
+mod fire;
+mod life;
+mod mouth;
+mod particles;
+mod plasma;
+mod script;
 mod sequence;
 mod smile;
+#[cfg(test)]
+mod snapshots;
+mod spectrum;
+mod sprite_sheet;
+mod starfield;
 mod startup;
 mod tictactoe;
 mod time;
+mod timer;
+mod tween;
 
 use embedded_graphics::{
     pixelcolor::{raw::ToBytes, Rgb888},
-    prelude::{DrawTarget, OriginDimensions, Size},
+    prelude::{Dimensions, DrawTarget, OriginDimensions, Point, PointsIter, Size},
+    primitives::Rectangle,
 };
 use image::RgbImage;
 
 pub trait Animation {
-    fn should_execute(&self) -> bool;
-    fn next_frame(&mut self) -> Option<RgbImage>;
+    fn should_execute(&self, ctx: &AnimationContext) -> bool;
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage>;
 
-    fn reload(&mut self) {}
+    fn reload(&mut self, _ctx: &mut AnimationContext) {}
 }
 
 #[derive(Clone)]
@@ -31,6 +48,17 @@ impl ImageBuffer {
     pub fn into_image(self) -> RgbImage {
         RgbImage::from_raw(64, 32, self.0).expect("Invalid buffer size")
     }
+
+    fn put(&mut self, point: Point, color: Rgb888) {
+        // Silently drop anything drawn outside of the panel
+        if !(0..64).contains(&point.x) || !(0..32).contains(&point.y) {
+            return;
+        }
+
+        let index = point.y as usize * 64 + point.x as usize;
+        let buff = &mut self.0[index * 3..index * 3 + 3];
+        buff.copy_from_slice(&color.to_be_bytes());
+    }
 }
 
 impl Default for ImageBuffer {
@@ -56,21 +84,60 @@ impl DrawTarget for ImageBuffer {
     where
         I: IntoIterator<Item = embedded_graphics::Pixel<Self::Color>>,
     {
-        pixels.into_iter().for_each(|p| {
-            let index = p.0.y as usize * 64 + p.0.x as usize;
-            let buff = &mut self.0[index * 3..index * 3 + 3];
-            buff.copy_from_slice(&p.1.to_be_bytes());
-        });
+        pixels.into_iter().for_each(|p| self.put(p.0, p.1));
+
+        Ok(())
+    }
+
+    // Only the part of `area` that is on the panel is visited, the default visits every point
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        for point in area.intersection(&self.bounding_box()).points() {
+            self.put(point, color);
+        }
+
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let panel = area.intersection(&self.bounding_box());
+
+        // Colors are given for every point of `area`, so stop at the last row on the panel
+        let points = area
+            .points()
+            .take_while(|point| panel.bottom_right().is_some_and(|end| point.y <= end.y));
+
+        for (point, color) in points.zip(colors) {
+            if panel.contains(point) {
+                self.put(point, color);
+            }
+        }
 
         Ok(())
     }
 }
 
+pub use aseprite::*;
 pub use blocks::*;
+pub use combinators::*;
+pub use context::*;
 pub use eyes::*;
 pub use falling::*;
+pub use fire::*;
+pub use life::*;
+pub use mouth::*;
+pub use particles::*;
+pub use plasma::*;
+pub use script::*;
 pub use sequence::*;
 pub use smile::*;
+pub use spectrum::*;
+pub use sprite_sheet::*;
+pub use starfield::*;
 pub use startup::*;
 pub use tictactoe::*;
 pub use time::*;
+pub use timer::*;
+pub use tween::*;
@@ -0,0 +1,188 @@
+use std::time::Duration;
+
+use image::{Rgb, RgbImage};
+
+use crate::audio::{self, Analyzer, AudioBuffer};
+
+use super::{Animation, AnimationContext};
+
+const FRAME_TIME: Duration = Duration::from_millis(40);
+
+// About 23ms of audio at 44.1kHz, short enough to follow single syllables
+const BLOCK_SIZE: usize = 1024;
+
+// Voice quieter than this keeps the mouth closed, louder than the top opens it all the way
+const SILENCE_DB: f32 = -45.0;
+const LOUD_DB: f32 = -15.0;
+
+// How much the mouth closes per frame when the voice gets quieter
+const RELEASE: f32 = 0.25;
+
+// Where the first two formants of a vowel are searched for
+const F1_RANGE: (f32, f32) = (200.0, 1000.0);
+const F2_RANGE: (f32, f32) = (1000.0, 3000.0);
+
+// The mouth fits in the gap between the eyes of `EyesAnimation`
+const CENTER: (f32, f32) = (31.5, 24.0);
+
+const COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// Mouth shapes for the vowels that can be told apart by their formants
+#[derive(Clone, Copy, PartialEq)]
+enum Shape {
+    Closed,
+
+    /// Open wide, like in "father"
+    A,
+
+    /// Wide and flat, also used for the "ee" in "see"
+    E,
+
+    /// Round, like in "go"
+    O,
+
+    /// Small and round, like in "food"
+    U,
+}
+
+impl Shape {
+    /// Largest width and height of the shape in pixels, when fully open
+    fn size(&self) -> (f32, f32) {
+        match self {
+            Self::Closed => (10.0, 0.0),
+            Self::A => (12.0, 10.0),
+            Self::E => (14.0, 5.0),
+            Self::O => (9.0, 9.0),
+            Self::U => (6.0, 6.0),
+        }
+    }
+
+    /// Guesses the vowel from the frequencies of its first two formants
+    fn from_formants(f1: f32, f2: f32) -> Self {
+        if f1 >= 650.0 {
+            Self::A
+        } else if f2 >= 1800.0 {
+            Self::E
+        } else if f1 >= 450.0 {
+            Self::O
+        } else {
+            Self::U
+        }
+    }
+}
+
+/// A mouth that moves along with the voice coming in through an [`AudioBuffer`]
+///
+/// It only draws the mouth, on black, in the gap between the eyes of the [`EyesAnimation`]. Put
+/// it on top of the eyes with an [`OverlayAnimation`] to get a talking face.
+///
+/// [`EyesAnimation`]: super::EyesAnimation
+/// [`OverlayAnimation`]: super::OverlayAnimation
+pub struct MouthAnimation {
+    audio: AudioBuffer,
+    analyzer: Analyzer,
+
+    shape: Shape,
+
+    /// How far the mouth is open, from 0.0 to 1.0
+    openness: f32,
+
+    last_frame: Duration,
+}
+
+impl MouthAnimation {
+    pub fn new(audio: AudioBuffer) -> Self {
+        Self {
+            audio,
+            analyzer: Analyzer::new(BLOCK_SIZE),
+
+            shape: Shape::Closed,
+            openness: 0.0,
+
+            last_frame: Duration::ZERO,
+        }
+    }
+
+    fn listen(&mut self) {
+        let samples = self.audio.latest(BLOCK_SIZE);
+        let loudness = audio::to_db(audio::rms(&samples));
+        let level = ((loudness - SILENCE_DB) / (LOUD_DB - SILENCE_DB)).clamp(0.0, 1.0);
+
+        // Open right away on a new syllable, but close a bit slower so it doesn't flicker
+        self.openness = level.max(self.openness - RELEASE);
+
+        if level == 0.0 {
+            if self.openness == 0.0 {
+                self.shape = Shape::Closed;
+            }
+
+            return;
+        }
+
+        let magnitudes = self.analyzer.magnitudes(&samples);
+        let bin_width = self.analyzer.bin_width(self.audio.sample_rate());
+
+        let f1 = loudest_frequency(&magnitudes, bin_width, F1_RANGE);
+        let f2 = loudest_frequency(&magnitudes, bin_width, F2_RANGE);
+
+        self.shape = Shape::from_formants(f1, f2);
+    }
+
+    fn draw(&self) -> RgbImage {
+        let mut image = RgbImage::new(64, 32);
+
+        let (width, height) = self.shape.size();
+        let height = (height * self.openness).max(2.0);
+
+        let (rx, ry) = (width / 2.0, height / 2.0);
+
+        // A closed mouth is only the outline, which is 2 pixels thick like the eyes
+        let (inner_rx, inner_ry) = (rx - 2.0, ry - 2.0);
+
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let dx = x as f32 - CENTER.0;
+            let dy = y as f32 - CENTER.1;
+
+            let outer = (dx / rx).powi(2) + (dy / ry).powi(2);
+            let inner = if inner_rx > 0.0 && inner_ry > 0.0 {
+                (dx / inner_rx).powi(2) + (dy / inner_ry).powi(2)
+            } else {
+                f32::INFINITY
+            };
+
+            if outer <= 1.0 && inner > 1.0 {
+                *pixel = COLOR;
+            }
+        }
+
+        image
+    }
+}
+
+impl Animation for MouthAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        ctx.elapsed(self.last_frame) > FRAME_TIME
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        self.listen();
+        self.last_frame = ctx.now();
+
+        Some(self.draw())
+    }
+}
+
+/// Frequency of the loudest bin between `range.0` and `range.1` Hz
+fn loudest_frequency(magnitudes: &[f32], bin_width: f32, range: (f32, f32)) -> f32 {
+    let first = ((range.0 / bin_width) as usize).min(magnitudes.len() - 1);
+    let last = ((range.1 / bin_width) as usize).clamp(first + 1, magnitudes.len());
+
+    let index = magnitudes[first..last]
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(index, _)| first + index)
+        .unwrap_or(first);
+
+    index as f32 * bin_width
+}
@@ -0,0 +1,478 @@
+use std::{collections::VecDeque, ops::Range, time::Duration};
+
+use image::{Rgb, RgbImage};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::matrix::color_utils::Palette;
+
+use super::{tween::Tween, Animation, AnimationContext};
+
+const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+const BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+
+/// How an emitter decides when to create particles
+pub enum Spawn {
+    /// On average this many particles per second, each at a random spot in the area
+    Continuous(f32),
+
+    /// Every `interval` seconds, `count` particles from a single random spot in the area
+    Bursts {
+        interval: Range<f32>,
+        count: Range<u32>,
+    },
+}
+
+pub enum ParticleColor {
+    Fixed(Rgb<u8>),
+
+    /// A random palette color per particle, or per burst for [`Spawn::Bursts`]
+    Palette,
+}
+
+/// Creates particles and describes how they look during their life
+pub struct Emitter {
+    pub spawn: Spawn,
+
+    /// Where particles appear, ranges of pixel centers on the X and Y axis
+    pub area: (Range<f32>, Range<f32>),
+
+    /// Direction in degrees, 0 is right and 90 is down
+    pub angle: Range<f32>,
+
+    /// Speed in pixels per second
+    pub speed: Range<f32>,
+
+    /// Lifetime in seconds, particles also die once they and their trail have left the panel
+    pub lifetime: Range<f32>,
+
+    /// Random sideways acceleration, in pixels per second squared, for fluttering particles
+    pub wobble: f32,
+
+    pub color: ParticleColor,
+
+    /// Multiplied with the particle color, by fraction of the lifetime that has passed
+    pub color_over_life: Vec<(f32, Rgb<u8>)>,
+
+    /// Amount of pixels drawn per particle, including the particle itself
+    pub trail: usize,
+}
+
+impl Emitter {
+    fn color_at(&self, life: f32) -> Rgb<u8> {
+        let mut from = match self.color_over_life.first() {
+            Some(stop) => *stop,
+            None => return WHITE,
+        };
+
+        for &(at, color) in &self.color_over_life {
+            if life < at {
+                let progress = (life - from.0) / (at - from.0);
+
+                return from.1.tween(color, progress);
+            }
+
+            from = (at, color);
+        }
+
+        from.1
+    }
+}
+
+struct Particle {
+    emitter: usize,
+
+    position: (f32, f32),
+    velocity: (f32, f32),
+
+    age: f32,
+    lifetime: f32,
+    color: Rgb<u8>,
+
+    /// Pixels the particle has been on, newest first
+    trail: VecDeque<(i32, i32)>,
+}
+
+impl Particle {
+    fn pixel(&self) -> (i32, i32) {
+        (
+            self.position.0.round() as i32,
+            self.position.1.round() as i32,
+        )
+    }
+
+    fn record_trail(&mut self, length: usize) {
+        let pixel = self.pixel();
+
+        if self.trail.front() != Some(&pixel) {
+            self.trail.push_front(pixel);
+            self.trail.truncate(length.max(1));
+        }
+    }
+}
+
+/// A set of emitters and the particles they have created, with shared physics
+pub struct ParticleSystem {
+    emitters: Vec<Emitter>,
+    particles: Vec<Particle>,
+
+    // Seconds until the next burst, per emitter
+    next_burst: Vec<f32>,
+
+    /// Acceleration in pixels per second squared
+    pub gravity: (f32, f32),
+
+    /// Fraction of the velocity that is lost per second
+    pub drag: f32,
+
+    pub palette: Palette,
+}
+
+impl ParticleSystem {
+    pub fn new(palette: Palette) -> Self {
+        Self {
+            emitters: vec![],
+            particles: vec![],
+            next_burst: vec![],
+
+            gravity: (0.0, 0.0),
+            drag: 0.0,
+
+            palette,
+        }
+    }
+
+    pub fn with_physics(mut self, gravity: (f32, f32), drag: f32) -> Self {
+        self.gravity = gravity;
+        self.drag = drag;
+        self
+    }
+
+    pub fn with_emitter(mut self, emitter: Emitter) -> Self {
+        self.emitters.push(emitter);
+        self.next_burst.push(0.0);
+        self
+    }
+
+    /// Moves all particles forward by `dt` seconds, removes dead ones and spawns new ones
+    pub fn update<R: Rng>(&mut self, dt: f32, rng: &mut R) {
+        let drag = (1.0 - self.drag * dt).max(0.0);
+
+        for particle in &mut self.particles {
+            let emitter = &self.emitters[particle.emitter];
+
+            particle.age += dt;
+
+            let wobble = sample(rng, -emitter.wobble..emitter.wobble);
+            particle.velocity.0 = (particle.velocity.0 + (self.gravity.0 + wobble) * dt) * drag;
+            particle.velocity.1 = (particle.velocity.1 + self.gravity.1 * dt) * drag;
+
+            particle.position.0 += particle.velocity.0 * dt;
+            particle.position.1 += particle.velocity.1 * dt;
+
+            particle.record_trail(emitter.trail);
+        }
+
+        let emitters = &self.emitters;
+        self.particles.retain(|particle| {
+            let margin = emitters[particle.emitter].trail as i32;
+            let (x, y) = particle.pixel();
+
+            particle.age < particle.lifetime
+                && (-margin..64 + margin).contains(&x)
+                && (-margin..32 + margin).contains(&y)
+        });
+
+        for index in 0..self.emitters.len() {
+            self.spawn(index, dt, rng);
+        }
+    }
+
+    fn spawn<R: Rng>(&mut self, index: usize, dt: f32, rng: &mut R) {
+        let emitter = &self.emitters[index];
+        let mut spawned = vec![];
+
+        match &emitter.spawn {
+            Spawn::Continuous(rate) => {
+                let expected = rate * dt;
+                let count = expected as u32 + (rng.gen::<f32>() < expected.fract()) as u32;
+
+                for _ in 0..count {
+                    let position = (
+                        sample(rng, emitter.area.0.clone()),
+                        sample(rng, emitter.area.1.clone()),
+                    );
+                    let color = self.pick_color(index, rng);
+
+                    spawned.push((position, color));
+                }
+            }
+
+            Spawn::Bursts { interval, count } => {
+                self.next_burst[index] -= dt;
+
+                if self.next_burst[index] <= 0.0 {
+                    self.next_burst[index] = sample(rng, interval.clone());
+
+                    let count = if count.start < count.end {
+                        rng.gen_range(count.clone())
+                    } else {
+                        count.start
+                    };
+
+                    let position = (
+                        sample(rng, emitter.area.0.clone()),
+                        sample(rng, emitter.area.1.clone()),
+                    );
+                    let color = self.pick_color(index, rng);
+
+                    spawned.extend((0..count).map(|_| (position, color)));
+                }
+            }
+        }
+
+        for (position, color) in spawned {
+            self.emit(index, position, color, rng);
+        }
+    }
+
+    fn pick_color<R: Rng>(&self, index: usize, rng: &mut R) -> Rgb<u8> {
+        match self.emitters[index].color {
+            ParticleColor::Fixed(color) => color,
+            ParticleColor::Palette => self.palette.pick(rng),
+        }
+    }
+
+    fn emit<R: Rng>(&mut self, index: usize, position: (f32, f32), color: Rgb<u8>, rng: &mut R) {
+        let emitter = &self.emitters[index];
+
+        let angle = sample(rng, emitter.angle.clone()).to_radians();
+        let speed = sample(rng, emitter.speed.clone());
+
+        let mut particle = Particle {
+            emitter: index,
+
+            position,
+            velocity: (angle.cos() * speed, angle.sin() * speed),
+
+            age: 0.0,
+            lifetime: sample(rng, emitter.lifetime.clone()),
+            color,
+
+            trail: VecDeque::new(),
+        };
+
+        particle.record_trail(emitter.trail);
+        self.particles.push(particle);
+    }
+
+    /// Draws all particles with their trails, which fade out towards the end
+    pub fn draw(&self, image: &mut RgbImage) {
+        for particle in &self.particles {
+            let emitter = &self.emitters[particle.emitter];
+
+            let life = if particle.lifetime.is_finite() {
+                particle.age / particle.lifetime
+            } else {
+                0.0
+            };
+
+            let tint = emitter.color_at(life);
+            let length = emitter.trail.max(1);
+
+            for (i, (x, y)) in particle.trail.iter().enumerate().rev() {
+                if !(0..image.width() as i32).contains(x) || !(0..image.height() as i32).contains(y)
+                {
+                    continue;
+                }
+
+                let modifier = (length - i) as f32 / length as f32;
+
+                let channel = |c: usize| {
+                    (particle.color.0[c] as f32 * tint.0[c] as f32 / 255.0 * modifier) as u8
+                };
+
+                image.put_pixel(
+                    *x as u32,
+                    *y as u32,
+                    Rgb([channel(0), channel(1), channel(2)]),
+                );
+            }
+        }
+    }
+}
+
+/// Picks a value in `range`, or its start when the range is empty
+fn sample<R: Rng>(rng: &mut R, range: Range<f32>) -> f32 {
+    if range.start < range.end {
+        rng.gen_range(range)
+    } else {
+        range.start
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ParticlePreset {
+    Rain,
+    Fireworks,
+    Snow,
+    Confetti,
+    Sparks,
+}
+
+impl ParticlePreset {
+    /// The preset that comes after this one when cycling through them on the deck
+    ///
+    /// Rain is skipped, as it has its own key.
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Rain | Self::Sparks => Self::Fireworks,
+            Self::Fireworks => Self::Snow,
+            Self::Snow => Self::Confetti,
+            Self::Confetti => Self::Sparks,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Rain => "Rain",
+            Self::Fireworks => "Boom",
+            Self::Snow => "Snow",
+            Self::Confetti => "Party",
+            Self::Sparks => "Sparks",
+        }
+    }
+
+    fn frame_time(&self) -> Duration {
+        match self {
+            Self::Rain => Duration::from_millis(50),
+            _ => Duration::from_millis(25),
+        }
+    }
+
+    fn system(&self, palette: Palette) -> ParticleSystem {
+        let system = ParticleSystem::new(palette);
+
+        match self {
+            // One pixel per frame, with a 2% chance per column per frame of a new drop
+            Self::Rain => system.with_emitter(Emitter {
+                spawn: Spawn::Continuous(64.0 * 0.02 * 20.0),
+                area: (-0.5..63.5, 0.0..0.0),
+                angle: 90.0..90.0,
+                speed: 20.0..20.0,
+                lifetime: f32::INFINITY..f32::INFINITY,
+                wobble: 0.0,
+                color: ParticleColor::Palette,
+                color_over_life: vec![],
+                trail: 10,
+            }),
+
+            Self::Fireworks => system.with_physics((0.0, 12.0), 1.2).with_emitter(Emitter {
+                spawn: Spawn::Bursts {
+                    interval: 0.4..1.2,
+                    count: 25..40,
+                },
+                area: (12.0..52.0, 5.0..18.0),
+                angle: 0.0..360.0,
+                speed: 6.0..22.0,
+                lifetime: 0.8..1.6,
+                wobble: 0.0,
+                color: ParticleColor::Palette,
+                color_over_life: vec![(0.0, WHITE), (0.6, WHITE), (1.0, BLACK)],
+                trail: 3,
+            }),
+
+            // Terminal velocity is gravity / drag
+            Self::Snow => system.with_physics((0.0, 3.0), 0.5).with_emitter(Emitter {
+                spawn: Spawn::Continuous(8.0),
+                area: (-0.5..63.5, -1.0..-1.0),
+                angle: 80.0..100.0,
+                speed: 4.0..6.0,
+                lifetime: f32::INFINITY..f32::INFINITY,
+                wobble: 12.0,
+                color: ParticleColor::Fixed(Rgb([200, 200, 255])),
+                color_over_life: vec![],
+                trail: 1,
+            }),
+
+            Self::Confetti => system.with_physics((0.0, 8.0), 0.8).with_emitter(Emitter {
+                spawn: Spawn::Continuous(30.0),
+                area: (-0.5..63.5, -1.0..-1.0),
+                angle: 60.0..120.0,
+                speed: 5.0..10.0,
+                lifetime: f32::INFINITY..f32::INFINITY,
+                wobble: 30.0,
+                color: ParticleColor::Palette,
+                color_over_life: vec![],
+                trail: 1,
+            }),
+
+            Self::Sparks => system.with_physics((0.0, 40.0), 0.0).with_emitter(Emitter {
+                spawn: Spawn::Bursts {
+                    interval: 0.05..0.3,
+                    count: 3..8,
+                },
+                area: (28.0..36.0, 31.0..31.0),
+                angle: 240.0..300.0,
+                speed: 20.0..40.0,
+                lifetime: 0.6..1.2,
+                wobble: 0.0,
+                color: ParticleColor::Fixed(WHITE),
+                color_over_life: vec![
+                    (0.0, WHITE),
+                    (0.3, Rgb([255, 200, 0])),
+                    (0.7, Rgb([255, 40, 0])),
+                    (1.0, BLACK),
+                ],
+                trail: 3,
+            }),
+        }
+    }
+}
+
+pub struct ParticleAnimation {
+    system: ParticleSystem,
+    frame_time: Duration,
+
+    last_frame: Duration,
+}
+
+impl ParticleAnimation {
+    pub fn new(preset: ParticlePreset, palette: Palette) -> Self {
+        Self::from_system(preset.system(palette), preset.frame_time())
+    }
+
+    /// Runs a custom particle system, which is moved forward by `frame_time` every frame
+    pub fn from_system(system: ParticleSystem, frame_time: Duration) -> Self {
+        Self {
+            system,
+            frame_time,
+
+            last_frame: Duration::ZERO,
+        }
+    }
+}
+
+impl Animation for ParticleAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        ctx.elapsed(self.last_frame) > self.frame_time
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        // Always step by the target frame time, so that particles move by whole pixels
+        self.system
+            .update(self.frame_time.as_secs_f32(), &mut ctx.rng);
+
+        let mut image = RgbImage::new(64, 32);
+        self.system.draw(&mut image);
+
+        self.last_frame = ctx.now();
+
+        Some(image)
+    }
+
+    fn reload(&mut self, ctx: &mut AnimationContext) {
+        self.system.palette.reseed(&mut ctx.rng);
+    }
+}
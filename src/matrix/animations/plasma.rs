@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::RgbColor};
+use image::{Rgb, RgbImage};
+
+use crate::{
+    image::hsl_to_rgb,
+    matrix::color_utils::{self, Palette},
+};
+
+use super::{Animation, AnimationContext};
+
+/// Classic demoscene plasma, a sum of sine waves mapped onto colors
+///
+/// Random palettes cycle through the full hue range, other palettes through their own colors.
+pub struct PlasmaAnimation {
+    palette: Palette,
+    table: Vec<Rgb888>,
+    speed: f32,
+
+    start: Option<Duration>,
+    last_frame: Duration,
+}
+
+impl PlasmaAnimation {
+    pub fn new(palette: Palette, speed: f32) -> Self {
+        Self {
+            palette,
+            table: color_utils::generate_table(&palette),
+            speed,
+
+            start: None,
+            last_frame: Duration::ZERO,
+        }
+    }
+
+    fn color(&self, value: f32, t: f32) -> Rgb<u8> {
+        if let Palette::Random(_) = self.palette {
+            let hue = (value * 360.0 + t * 20.0).rem_euclid(360.0);
+
+            return hsl_to_rgb(hue as u16, 1.0, 0.5);
+        }
+
+        let len = self.table.len();
+        let index = (value * len as f32 + t * 10.0) as usize % len;
+        let color = self.table[index];
+
+        Rgb([color.r(), color.g(), color.b()])
+    }
+}
+
+impl Animation for PlasmaAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        // Target: 40 FPS
+        ctx.elapsed(self.last_frame) > Duration::from_millis(25)
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        let start = *self.start.get_or_insert(ctx.now());
+        let t = ctx.elapsed(start).as_secs_f32() * self.speed;
+
+        let mut image = RgbImage::new(64, 32);
+
+        // Center of the circular wave moves around the panel
+        let (cx, cy) = (2.0 * (t / 5.0).sin(), (t / 3.0).cos());
+
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            let (fx, fy) = (x as f32 / 8.0, y as f32 / 8.0);
+
+            let value = (fx + t).sin()
+                + ((fy + t) / 2.0).sin()
+                + ((fx + fy + t) / 2.0).sin()
+                + (((fx - 4.0 - cx).powi(2) + (fy - 2.0 - cy).powi(2)).sqrt() + t).sin();
+
+            // -4.0..4.0 to 0.0..1.0
+            *pixel = self.color((value + 4.0) / 8.0, t);
+        }
+
+        self.last_frame = ctx.now();
+
+        Some(image)
+    }
+
+    fn reload(&mut self, ctx: &mut AnimationContext) {
+        self.palette.reseed(&mut ctx.rng);
+        self.table = color_utils::generate_table(&self.palette);
+    }
+}
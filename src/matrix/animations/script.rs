@@ -0,0 +1,429 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use embedded_graphics::{
+    pixelcolor::Rgb888,
+    prelude::{DrawTarget, Point, Primitive, RgbColor, Size},
+    primitives::{Circle, Line, PrimitiveStyle, Rectangle},
+    Drawable, Pixel,
+};
+use image::RgbImage;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rhai::{CallFnOptions, Dynamic, Engine, Map, Scope, AST, FLOAT, INT};
+
+use crate::{
+    image::hsl_to_rgb,
+    matrix::color_utils::{self, Palette},
+};
+
+use super::{Animation, AnimationContext, ImageBuffer};
+
+/// Directory that is scanned for `.rhai` matrix scripts
+pub const SCRIPTS_DIR: &str = "scripts";
+
+const DEFAULT_FPS: INT = 30;
+
+// How often the script file is checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+// Limits for a single run of the script, a script that goes past them stops like one with an
+// error instead of hanging the matrix
+const MAX_OPERATIONS: u64 = 500_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_EXPR_DEPTH: usize = 64;
+
+// Coordinates and sizes are clamped to this, far enough off the panel for anything that is
+// partly on it and small enough that drawing can't overflow
+const MAX_COORD: INT = 1024;
+
+/// An animation that is driven by a [Rhai](https://rhai.rs) script
+///
+/// The script must define a `render(t, frame)` function, which is called every frame with the
+/// seconds since the script was (re)loaded as a float and the frame number as an integer.
+/// An optional `init()` function is called once after loading. Both functions can keep state
+/// between frames in `this`, which starts out as an empty object map.
+///
+/// A global `const FPS = 30;` can be used to change the frame rate.
+///
+/// Colors are integers in the `0xRRGGBB` form. The following functions are available:
+///
+/// - `clear()`, `fill(color)`
+/// - `pixel(x, y, color)`, `line(x0, y0, x1, y1, color)`
+/// - `rect(x, y, w, h, color)`, `circle(x, y, radius, color)`, coordinates and sizes are
+///   clamped to [`MAX_COORD`]
+/// - `rgb(r, g, b)`, `hsl(hue, saturation, lightness)` where saturation and lightness are floats
+/// - `palette(i)` and `palette_len()`, a looping gradient through the selected palette
+/// - `rand(min, max)` for an integer in `min..max`, `rand_float()` for a float in `0..1`
+///
+/// The script file is reloaded automatically when it changes on disk. Every call of `init` or
+/// `render` may take at most [`MAX_OPERATIONS`] steps, a script that loops forever stops with
+/// an error.
+pub struct ScriptAnimation {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_check: Duration,
+
+    engine: Engine,
+    script: Option<(AST, Scope<'static>)>,
+    this: Dynamic,
+
+    canvas: Arc<Mutex<ImageBuffer>>,
+    palette: Palette,
+    table: Arc<Mutex<Vec<Rgb888>>>,
+    rng: Arc<Mutex<StdRng>>,
+
+    frame_time: Duration,
+    start: Option<Duration>,
+    frame: INT,
+    last_frame: Duration,
+}
+
+impl ScriptAnimation {
+    pub fn new(path: PathBuf, palette: Palette) -> Self {
+        let canvas = Arc::new(Mutex::new(ImageBuffer::new()));
+        let table = Arc::new(Mutex::new(color_utils::generate_table(&palette)));
+
+        // Reseeded from the animation context before every frame
+        let rng = Arc::new(Mutex::new(StdRng::seed_from_u64(0)));
+
+        let mut animation = Self {
+            path,
+            modified: None,
+            last_check: Duration::ZERO,
+
+            engine: create_engine(&canvas, &table, &rng),
+            script: None,
+            this: Dynamic::from_map(Map::new()),
+
+            canvas,
+            palette,
+            table,
+            rng,
+
+            frame_time: frame_time(DEFAULT_FPS),
+            start: None,
+            frame: 0,
+            last_frame: Duration::ZERO,
+        };
+
+        animation.load();
+        animation
+    }
+
+    /// (Re)compiles the script and runs its top level statements and `init` function
+    ///
+    /// Errors are printed, and leave the panel black until the script is fixed
+    fn load(&mut self) {
+        self.modified = modified_time(&self.path);
+        self.script = None;
+        self.this = Dynamic::from_map(Map::new());
+        self.start = None;
+        self.frame = 0;
+
+        *self.canvas.lock().expect("canvas lock poisoned") = ImageBuffer::new();
+
+        let source = match std::fs::read_to_string(&self.path) {
+            Ok(source) => source,
+            Err(why) => {
+                log::warn!("Failed to read script {}: {why}", self.path.display());
+                return;
+            }
+        };
+
+        let ast = match self.engine.compile(source) {
+            Ok(ast) => ast,
+            Err(why) => {
+                log::warn!("Failed to compile script {}: {why}", self.path.display());
+                return;
+            }
+        };
+
+        let mut scope = Scope::new();
+
+        if let Err(why) = self.engine.run_ast_with_scope(&mut scope, &ast) {
+            log::warn!("Failed to run script {}: {why}", self.path.display());
+            return;
+        }
+
+        self.frame_time = frame_time(scope.get_value::<INT>("FPS").unwrap_or(DEFAULT_FPS));
+
+        if ast.iter_functions().any(|f| f.name == "init") {
+            let options = CallFnOptions::new()
+                .eval_ast(false)
+                .bind_this_ptr(&mut self.this);
+
+            if let Err(why) =
+                self.engine
+                    .call_fn_with_options::<Dynamic>(options, &mut scope, &ast, "init", ())
+            {
+                log::warn!("Script {} failed in init: {why}", self.path.display());
+                return;
+            }
+        }
+
+        self.script = Some((ast, scope));
+    }
+}
+
+impl Animation for ScriptAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        ctx.elapsed(self.last_frame) > self.frame_time
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        if ctx.elapsed(self.last_check) > RELOAD_INTERVAL {
+            self.last_check = ctx.now();
+
+            if modified_time(&self.path) != self.modified {
+                self.load();
+            }
+        }
+
+        let start = *self.start.get_or_insert(ctx.now());
+        *self.rng.lock().expect("rng lock poisoned") = StdRng::seed_from_u64(ctx.rng.gen());
+
+        if let Some((ast, scope)) = &mut self.script {
+            let options = CallFnOptions::new()
+                .eval_ast(false)
+                .bind_this_ptr(&mut self.this);
+
+            let t = ctx.elapsed(start).as_secs_f64() as FLOAT;
+
+            if let Err(why) = self.engine.call_fn_with_options::<Dynamic>(
+                options,
+                scope,
+                ast,
+                "render",
+                (t, self.frame),
+            ) {
+                // Stop running the script, it will be picked up again once it changes
+                log::warn!("Script {} failed in render: {why}", self.path.display());
+                self.script = None;
+
+                *self.canvas.lock().expect("canvas lock poisoned") = ImageBuffer::new();
+            }
+        }
+
+        self.frame += 1;
+        self.last_frame = ctx.now();
+
+        let canvas = self.canvas.lock().expect("canvas lock poisoned");
+        Some(canvas.clone().into_image())
+    }
+
+    fn reload(&mut self, ctx: &mut AnimationContext) {
+        self.palette.reseed(&mut ctx.rng);
+        *self.table.lock().expect("palette lock poisoned") =
+            color_utils::generate_table(&self.palette);
+    }
+}
+
+/// Lists all scripts in [`SCRIPTS_DIR`], sorted by file name
+pub fn list_scripts() -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(SCRIPTS_DIR) else {
+        return vec![];
+    };
+
+    let mut scripts = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+        .collect::<Vec<_>>();
+
+    scripts.sort();
+    scripts
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn frame_time(fps: INT) -> Duration {
+    Duration::from_secs_f64(1.0 / fps.clamp(1, 120) as f64)
+}
+
+fn to_color(color: INT) -> Rgb888 {
+    Rgb888::new((color >> 16) as u8, (color >> 8) as u8, color as u8)
+}
+
+fn from_color(color: Rgb888) -> INT {
+    ((color.r() as INT) << 16) | ((color.g() as INT) << 8) | color.b() as INT
+}
+
+fn coord(value: INT) -> i32 {
+    value.clamp(-MAX_COORD, MAX_COORD) as i32
+}
+
+fn point(x: INT, y: INT) -> Point {
+    Point::new(coord(x), coord(y))
+}
+
+fn length(value: INT) -> u32 {
+    value.clamp(0, MAX_COORD) as u32
+}
+
+fn create_engine(
+    canvas: &Arc<Mutex<ImageBuffer>>,
+    table: &Arc<Mutex<Vec<Rgb888>>>,
+    rng: &Arc<Mutex<StdRng>>,
+) -> Engine {
+    let mut engine = Engine::new();
+
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_expr_depths(MAX_EXPR_DEPTH, MAX_EXPR_DEPTH);
+
+    // Drawing
+    let c = canvas.clone();
+    engine.register_fn("clear", move || {
+        *c.lock().expect("canvas lock poisoned") = ImageBuffer::new();
+    });
+
+    let c = canvas.clone();
+    engine.register_fn("fill", move |color: INT| {
+        c.lock()
+            .expect("canvas lock poisoned")
+            .clear(to_color(color))
+            .ok();
+    });
+
+    let c = canvas.clone();
+    engine.register_fn("pixel", move |x: INT, y: INT, color: INT| {
+        Pixel(point(x, y), to_color(color))
+            .draw(&mut *c.lock().expect("canvas lock poisoned"))
+            .ok();
+    });
+
+    let c = canvas.clone();
+    engine.register_fn(
+        "line",
+        move |x0: INT, y0: INT, x1: INT, y1: INT, color: INT| {
+            Line::new(point(x0, y0), point(x1, y1))
+                .into_styled(PrimitiveStyle::with_stroke(to_color(color), 1))
+                .draw(&mut *c.lock().expect("canvas lock poisoned"))
+                .ok();
+        },
+    );
+
+    let c = canvas.clone();
+    engine.register_fn("rect", move |x: INT, y: INT, w: INT, h: INT, color: INT| {
+        Rectangle::new(point(x, y), Size::new(length(w), length(h)))
+            .into_styled(PrimitiveStyle::with_fill(to_color(color)))
+            .draw(&mut *c.lock().expect("canvas lock poisoned"))
+            .ok();
+    });
+
+    let c = canvas.clone();
+    engine.register_fn("circle", move |x: INT, y: INT, radius: INT, color: INT| {
+        let diameter = length(radius).saturating_mul(2).saturating_add(1);
+
+        Circle::with_center(point(x, y), diameter)
+            .into_styled(PrimitiveStyle::with_fill(to_color(color)))
+            .draw(&mut *c.lock().expect("canvas lock poisoned"))
+            .ok();
+    });
+
+    // Colors
+    engine.register_fn("rgb", |r: INT, g: INT, b: INT| {
+        from_color(Rgb888::new(r as u8, g as u8, b as u8))
+    });
+
+    engine.register_fn("hsl", |h: INT, s: FLOAT, l: FLOAT| {
+        let rgb = hsl_to_rgb(h.rem_euclid(360) as u16, s as f32, l as f32);
+        from_color(Rgb888::new(rgb.0[0], rgb.0[1], rgb.0[2]))
+    });
+
+    let p = table.clone();
+    engine.register_fn("palette", move |i: INT| {
+        let table = p.lock().expect("palette lock poisoned");
+        from_color(table[i.rem_euclid(table.len() as INT) as usize])
+    });
+
+    let p = table.clone();
+    engine.register_fn("palette_len", move || {
+        p.lock().expect("palette lock poisoned").len() as INT
+    });
+
+    // Randomness
+    let r = rng.clone();
+    engine.register_fn("rand", move |min: INT, max: INT| {
+        if max <= min {
+            return min;
+        }
+
+        r.lock().expect("rng lock poisoned").gen_range(min..max)
+    });
+
+    let r = rng.clone();
+    engine.register_fn("rand_float", move || {
+        r.lock().expect("rng lock poisoned").gen::<FLOAT>()
+    });
+
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use image::Rgb;
+    use time::OffsetDateTime;
+
+    use super::*;
+    use crate::matrix::animations::{step_animation, FakeClock};
+
+    const TICK: Duration = Duration::from_millis(5);
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn script_limits() {
+        let dir = env::temp_dir().join(format!("feestje-script-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let scripts = [
+            ("loop", "fn render(t, frame) { fill(0xff0000); loop {} }"),
+            (
+                "recursion",
+                "fn render(t, frame) { fill(0xff0000); render(t, frame) }",
+            ),
+        ];
+
+        // Shapes far bigger than the panel are drawn as quickly as the part that is on it
+        let huge = 4_000_000_000_000_000_000i64;
+        let shapes = [
+            format!("rect(-{huge}, -{huge}, {huge}, {huge}, 0xff0000); rect(0, 0, {huge}, {huge}, 0xff0000)"),
+            format!("line(0, 0, {huge}, 0, 0xff0000); line(-{huge}, 1, 0, 1, 0xff0000)"),
+            format!("circle(0, 0, {huge}, 0xff0000); circle({huge}, -{huge}, 10, 0xff0000)"),
+        ];
+
+        for (name, source) in scripts {
+            let path = dir.join(format!("{name}.rhai"));
+            fs::write(&path, source).unwrap();
+
+            let clock = FakeClock::new(OffsetDateTime::UNIX_EPOCH);
+            let mut ctx = AnimationContext::with_clock(clock.clone(), 0);
+            let mut animation = ScriptAnimation::new(path, Palette::default());
+
+            // The script is stopped, and the panel is left black
+            let frame = step_animation(&mut animation, &mut ctx, &clock, TICK, TIMEOUT).unwrap();
+            assert!(frame == RgbImage::new(64, 32), "{name} script drew a frame");
+        }
+
+        for shape in shapes {
+            let path = dir.join("shape.rhai");
+            fs::write(&path, format!("fn render(t, frame) {{ {shape} }}")).unwrap();
+
+            let clock = FakeClock::new(OffsetDateTime::UNIX_EPOCH);
+            let mut ctx = AnimationContext::with_clock(clock.clone(), 0);
+            let mut animation = ScriptAnimation::new(path, Palette::default());
+
+            let frame = step_animation(&mut animation, &mut ctx, &clock, TICK, TIMEOUT).unwrap();
+            assert_eq!(*frame.get_pixel(0, 0), Rgb([255, 0, 0]), "{shape}");
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,69 +1,70 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
-use super::{Animation, BlocksAnimation, EyesAnimation, FallingAnimation, TimeAnimation};
+use crate::matrix::color_utils::Palette;
+
+use super::{
+    Animation, AnimationContext, BlocksAnimation, ClockFace, EyesAnimation, FallingAnimation,
+    FireAnimation, LifeAnimation, PlasmaAnimation, StarfieldAnimation, TimeAnimation,
+};
 
 pub struct SequenceAnimation {
-    time_animation: TimeAnimation,
-    eyes_animation: EyesAnimation,
-    falling_animation: FallingAnimation,
-    blocks_animation: BlocksAnimation,
+    animations: Vec<Box<dyn Animation + Send + Sync>>,
 
-    current_animation: u8,
-    animation_start: Instant,
+    current_animation: usize,
+    animation_start: Option<Duration>,
 }
 
 impl SequenceAnimation {
-    pub fn new() -> Self {
+    pub fn new(palette: Palette) -> Self {
+        Self::with_animations(vec![
+            Box::new(TimeAnimation::new(ClockFace::Digital, palette)),
+            Box::new(EyesAnimation::new()),
+            Box::new(FallingAnimation::new(palette)),
+            Box::new(BlocksAnimation::new(palette)),
+            Box::new(PlasmaAnimation::new(palette, 1.0)),
+            Box::new(FireAnimation::new(palette, 1.0)),
+            Box::new(StarfieldAnimation::new(palette, 1.0)),
+            Box::new(LifeAnimation::new(palette, 1.0)),
+        ])
+    }
+
+    /// Switches between `animations` every 30 seconds, in order
+    pub fn with_animations(animations: Vec<Box<dyn Animation + Send + Sync>>) -> Self {
         Self {
-            time_animation: TimeAnimation::new(false),
-            eyes_animation: EyesAnimation::new(),
-            falling_animation: FallingAnimation::new(),
-            blocks_animation: BlocksAnimation::new(),
+            animations,
 
             current_animation: 0,
-            animation_start: Instant::now(),
+            animation_start: None,
         }
     }
 }
 
 impl Default for SequenceAnimation {
     fn default() -> Self {
-        Self::new()
+        Self::new(Palette::default())
     }
 }
 
 impl Animation for SequenceAnimation {
-    fn should_execute(&self) -> bool {
-        match self.current_animation {
-            0 => self.time_animation.should_execute(),
-            1 => self.eyes_animation.should_execute(),
-            2 => self.falling_animation.should_execute(),
-            3 => self.blocks_animation.should_execute(),
-            _ => false,
-        }
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        self.animations
+            .get(self.current_animation)
+            .is_some_and(|animation| animation.should_execute(ctx))
     }
 
-    fn next_frame(&mut self) -> Option<image::RgbImage> {
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<image::RgbImage> {
+        let animation_start = *self.animation_start.get_or_insert(ctx.now());
+
         // Will not be exactly 30 seconds, as `should_execute` will cause time drift
-        if self.animation_start.elapsed() > Duration::from_secs(30) {
-            self.current_animation = (self.current_animation + 1) % 4;
-            self.animation_start = Instant::now();
+        if ctx.elapsed(animation_start) > Duration::from_secs(30) {
+            self.current_animation = (self.current_animation + 1) % self.animations.len();
+            self.animation_start = Some(ctx.now());
 
-            match self.current_animation {
-                0 => self.time_animation.reload(),
-                1 => self.eyes_animation.reload(),
-                2 => self.falling_animation.reload(),
-                3 => self.blocks_animation.reload(),
-                _ => {}
-            }
+            self.animations[self.current_animation].reload(ctx);
         }
 
-        match self.current_animation {
-            0 => self.time_animation.next_frame(),
-            1 => self.eyes_animation.next_frame(),
-            2 => self.falling_animation.next_frame(),
-            3 => self.blocks_animation.next_frame(),
-            _ => None,
-        }
+        self.animations
+            .get_mut(self.current_animation)?
+            .next_frame(ctx)
     }
 }
@@ -1,34 +1,49 @@
-use std::time::Instant;
+use std::time::Duration;
 
 use image::RgbImage;
 
-use crate::image::decode_bmp;
+use super::{
+    tween::{Easing, Sprite, Track},
+    Animation, AnimationContext, SpriteSheet,
+};
 
-use super::Animation;
+const SMILE_MANIFEST: &str = include_str!("../../../images/smile.toml");
+const SMILE_SHEET: &[u8] = include_bytes!("../../../images/smile.png");
 
-const SMILE_IMAGE: &[u8] = include_bytes!("../../../images/smile.bmp");
+// The strip scrolls in from the right, for the duration of its frame, and stops on its last 64
+// pixels
+const SCROLL_START: f32 = 44.0;
+const SCROLL_END: f32 = -192.0;
 
 pub struct SmileAnimation {
-    image: RgbImage,
-    last_frame: Instant,
+    smile: Sprite,
 
-    offset: isize,
+    start: Option<Duration>,
+    last_frame: Duration,
+    finished: bool,
 }
 
 impl SmileAnimation {
     pub fn new() -> Self {
-        let image = RgbImage::from_raw(
-            64 * 4,
-            32,
-            decode_bmp(SMILE_IMAGE).expect("Smile image corrupt"),
-        )
-        .expect("Smile image corrupt");
+        let sheet = SpriteSheet::parse(SMILE_MANIFEST, SMILE_SHEET)
+            .expect("Smile sprite sheet is embedded in the binary");
+        let frame = sheet
+            .frame("smile")
+            .expect("Smile sprite sheet has no smile");
+        let scroll = frame.tween.expect("Smile sprite sheet has no scroll time");
+
+        let smile = Sprite::new(frame.image.clone()).position(Track::new((SCROLL_START, 0.0)).to(
+            scroll,
+            (SCROLL_END, 0.0),
+            Easing::Linear,
+        ));
 
         Self {
-            image,
-            last_frame: Instant::now(),
+            smile,
 
-            offset: 44,
+            start: None,
+            last_frame: Duration::ZERO,
+            finished: false,
         }
     }
 }
@@ -40,26 +55,20 @@ impl Default for SmileAnimation {
 }
 
 impl Animation for SmileAnimation {
-    fn should_execute(&self) -> bool {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
         // Target: ~64FPS
-        self.last_frame.elapsed().as_millis() > 15 && self.offset >= -192
+        !self.finished && ctx.elapsed(self.last_frame).as_millis() > 15
     }
 
-    fn next_frame(&mut self) -> Option<image::RgbImage> {
-        let mut image = RgbImage::new(64, 32);
-
-        let x_dst = std::cmp::max(0, self.offset);
-        let x_src = std::cmp::max(0, -self.offset);
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<image::RgbImage> {
+        let start = *self.start.get_or_insert(ctx.now());
+        let t = ctx.elapsed(start);
 
-        for y in 0..32 {
-            for x in 0..64 - x_dst {
-                let pixel = self.image.get_pixel((x_src + x) as u32, y);
-                image.put_pixel((x_dst + x) as u32, y, *pixel);
-            }
-        }
+        let mut image = RgbImage::new(64, 32);
+        self.smile.draw(&mut image, t);
 
-        self.offset -= 1;
-        self.last_frame = Instant::now();
+        self.finished = t >= self.smile.duration();
+        self.last_frame = ctx.now();
 
         Some(image)
     }
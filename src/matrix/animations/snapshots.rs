@@ -0,0 +1,544 @@
+//! Golden image tests for the matrix animations
+//!
+//! Every animation is stepped with a [`FakeClock`] and a fixed seed, and selected frames are
+//! compared to the PNGs in `src/matrix/animations/snapshots/`. Run with `BLESS=1` to write the
+//! current frames as the new goldens after an intentional change.
+
+use std::{env, fs, path::PathBuf, time::Duration};
+
+use image::{Rgb, RgbImage};
+use time::{Date, Month, OffsetDateTime, Time};
+
+use crate::{audio::AudioBuffer, matrix::color_utils::Palette};
+
+use super::{
+    step_animation, Animation, AnimationContext, AsepriteAnimation, AsepriteFile, BlocksAnimation,
+    ClockFace, CountdownAnimation, Deadline, EyesAnimation, EyesControl, FakeClock,
+    FallingAnimation, FireAnimation, Gaze, LifeAnimation, MaskAnimation, Mood, MouthAnimation,
+    OverlayAnimation, ParticleAnimation, ParticlePreset, PlasmaAnimation, ScriptAnimation,
+    SequenceAnimation, SmileAnimation, SpectrumAnimation, SpectrumMode, SplitScreenAnimation,
+    StarfieldAnimation, StartupAnimation, StopwatchAnimation, TicTacToeAnimation, TimeAnimation,
+    Timer, Winner, SCRIPTS_DIR,
+};
+
+const SEED: u64 = 1023;
+const PALETTE: Palette = Palette::Random(1023);
+
+const TICK: Duration = Duration::from_millis(5);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+const SAMPLE_RATE: u32 = 44100;
+
+fn wall_time(hour: u8, minute: u8, second: u8) -> OffsetDateTime {
+    let date = Date::from_calendar_date(2023, Month::October, 21).unwrap();
+    let time = Time::from_hms(hour, minute, second).unwrap();
+
+    date.with_time(time).assume_utc()
+}
+
+/// `duration` of a sine wave, starting at phase 0
+fn sine(frequency: f32, amplitude: f32, duration: Duration) -> impl Iterator<Item = f32> {
+    let count = (SAMPLE_RATE as f32 * duration.as_secs_f32()) as usize;
+
+    (0..count).map(move |i| {
+        let t = i as f32 / SAMPLE_RATE as f32;
+
+        amplitude * (std::f32::consts::TAU * frequency * t).sin()
+    })
+}
+
+fn snapshot_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/matrix/animations/snapshots")
+}
+
+/// Steps `animation` from the start and checks the frames with the given indices against the
+/// goldens named `{name}_{index}.png`
+fn assert_frames(
+    name: &str,
+    mut animation: impl Animation,
+    start: OffsetDateTime,
+    frames: &[usize],
+) {
+    let clock = FakeClock::new(start);
+    let mut ctx = AnimationContext::with_clock(clock.clone(), SEED);
+
+    let last = *frames.iter().max().expect("no frames selected");
+
+    for index in 0..=last {
+        let Some(frame) = step_animation(&mut animation, &mut ctx, &clock, TICK, TIMEOUT) else {
+            panic!("{name} stopped after {index} frames, expected {}", last + 1);
+        };
+
+        if frames.contains(&index) {
+            assert_golden(&format!("{name}_{index}"), &frame);
+        }
+    }
+}
+
+fn assert_golden(name: &str, frame: &RgbImage) {
+    let dir = snapshot_dir();
+    let path = dir.join(format!("{name}.png"));
+
+    if env::var_os("BLESS").is_some() {
+        fs::create_dir_all(&dir).unwrap();
+        frame.save(&path).unwrap();
+
+        return;
+    }
+
+    let golden = match image::open(&path) {
+        Ok(golden) => golden.into_rgb8(),
+        Err(error) => panic!(
+            "Missing golden {}: {error}, run with BLESS=1 to create it",
+            path.display()
+        ),
+    };
+
+    if golden != *frame {
+        let actual = dir.join(format!("{name}.new.png"));
+        frame.save(&actual).unwrap();
+
+        let different = golden
+            .pixels()
+            .zip(frame.pixels())
+            .filter(|(a, b)| a != b)
+            .count();
+
+        panic!(
+            "{name} differs from its golden in {different} pixels, actual frame written to {}",
+            actual.display()
+        );
+    }
+}
+
+#[test]
+fn time_faces() {
+    let faces = [
+        ("digital", ClockFace::Digital),
+        ("analog", ClockFace::Analog),
+        ("binary", ClockFace::Binary),
+        ("date_time", ClockFace::DateTime),
+        ("progress", ClockFace::Progress),
+        ("hidden", ClockFace::Hidden),
+    ];
+
+    for (name, face) in faces {
+        assert_frames(
+            &format!("time_{name}"),
+            TimeAnimation::new(face, PALETTE),
+            wall_time(20, 23, 45),
+            &[0, 30],
+        );
+    }
+}
+
+#[test]
+fn time_early_morning() {
+    // Before 01:00 UTC, the clock is two hours ahead
+    assert_frames(
+        "time_early_morning",
+        TimeAnimation::new(ClockFace::Digital, PALETTE),
+        wall_time(0, 59, 58),
+        &[0],
+    );
+}
+
+#[test]
+fn tictactoe() {
+    let winners = [("x", Winner::X), ("o", Winner::O), ("draw", Winner::Draw)];
+
+    for (name, winner) in winners {
+        assert_frames(
+            &format!("tictactoe_{name}"),
+            TicTacToeAnimation::new(winner),
+            wall_time(20, 0, 0),
+            &[0, 8],
+        );
+    }
+}
+
+#[test]
+fn startup() {
+    let animation = StartupAnimation::load().unwrap();
+
+    assert_frames("startup", animation, wall_time(20, 0, 0), &[0, 12, 25]);
+}
+
+#[test]
+fn eyes() {
+    assert_frames(
+        "eyes",
+        EyesAnimation::new(),
+        wall_time(20, 0, 0),
+        &[0, 50, 53, 120],
+    );
+}
+
+#[test]
+fn eye_moods() {
+    let moods = [
+        ("happy", Mood::Happy, Gaze::Center),
+        ("angry", Mood::Angry, Gaze::Left),
+        ("sleepy", Mood::Sleepy, Gaze::Right),
+        ("surprised", Mood::Surprised, Gaze::Center),
+        ("love", Mood::Love, Gaze::Center),
+    ];
+
+    for (name, mood, gaze) in moods {
+        let control = EyesControl::new();
+        control.express(mood);
+        control.look(gaze);
+
+        assert_frames(
+            &format!("eyes_{name}"),
+            EyesAnimation::with_control(control),
+            wall_time(20, 0, 0),
+            &[0],
+        );
+    }
+
+    // The idle personality has moved on from neutral after half a minute
+    assert_frames(
+        "eyes_idle",
+        EyesAnimation::new(),
+        wall_time(20, 0, 0),
+        &[360],
+    );
+}
+
+#[test]
+fn smile() {
+    assert_frames(
+        "smile",
+        SmileAnimation::new(),
+        wall_time(20, 0, 0),
+        &[0, 20, 60],
+    );
+}
+
+#[test]
+fn falling() {
+    assert_frames(
+        "falling",
+        FallingAnimation::new(PALETTE),
+        wall_time(20, 0, 0),
+        &[0, 25, 100],
+    );
+}
+
+#[test]
+fn blocks() {
+    assert_frames(
+        "blocks",
+        BlocksAnimation::new(PALETTE),
+        wall_time(20, 0, 0),
+        &[0, 20, 100],
+    );
+}
+
+#[test]
+fn sequence() {
+    assert_frames(
+        "sequence",
+        SequenceAnimation::new(PALETTE),
+        wall_time(20, 0, 0),
+        &[0, 10],
+    );
+}
+
+#[test]
+fn countdown() {
+    let timer = Timer::new();
+    timer.start();
+
+    let animation = CountdownAnimation::new(
+        timer,
+        Deadline::After(Duration::from_secs(12)),
+        Box::new(BlocksAnimation::new(PALETTE)),
+    );
+
+    // Still counting, in the red final seconds, and the finale
+    assert_frames("countdown", animation, wall_time(20, 0, 0), &[0, 100, 400]);
+}
+
+#[test]
+fn countdown_to_a_time() {
+    // The timer is never started, the time comes anyway
+    let animation = CountdownAnimation::new(
+        Timer::new(),
+        Deadline::At(wall_time(20, 0, 12)),
+        Box::new(BlocksAnimation::new(PALETTE)),
+    );
+
+    // The same finale as the countdown that was started, in a golden of its own
+    assert_frames("countdown_at", animation, wall_time(20, 0, 0), &[400]);
+}
+
+#[test]
+fn stopwatch() {
+    let timer = Timer::new();
+    timer.start();
+
+    assert_frames(
+        "stopwatch",
+        StopwatchAnimation::new(timer),
+        wall_time(20, 0, 0),
+        &[0, 200],
+    );
+}
+
+#[test]
+fn script() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join(SCRIPTS_DIR)
+        .join("rainbow.rhai");
+
+    assert_frames(
+        "script_rainbow",
+        ScriptAnimation::new(path, PALETTE),
+        wall_time(20, 0, 0),
+        &[0, 40],
+    );
+}
+
+#[test]
+fn overlay() {
+    let animation = OverlayAnimation::new(
+        Box::new(FallingAnimation::new(PALETTE)),
+        Box::new(TimeAnimation::new(ClockFace::Digital, PALETTE)),
+        Rgb([0, 0, 0]),
+    );
+
+    assert_frames("overlay", animation, wall_time(20, 23, 45), &[0, 60]);
+}
+
+#[test]
+fn mask() {
+    let animation = MaskAnimation::new(
+        Box::new(BlocksAnimation::new(PALETTE)),
+        Box::new(TimeAnimation::new(ClockFace::Digital, PALETTE)),
+    );
+
+    assert_frames("mask", animation, wall_time(20, 23, 45), &[0, 60]);
+}
+
+#[test]
+fn split_screen() {
+    let animation = SplitScreenAnimation::new(
+        Box::new(TimeAnimation::new(ClockFace::Analog, PALETTE)),
+        Box::new(TicTacToeAnimation::new(Winner::Draw)),
+    );
+
+    assert_frames("split_screen", animation, wall_time(20, 23, 45), &[0]);
+}
+
+#[test]
+fn particles() {
+    let presets = [
+        ("fireworks", ParticlePreset::Fireworks),
+        ("snow", ParticlePreset::Snow),
+        ("confetti", ParticlePreset::Confetti),
+        ("sparks", ParticlePreset::Sparks),
+    ];
+
+    for (name, preset) in presets {
+        assert_frames(
+            &format!("particles_{name}"),
+            ParticleAnimation::new(preset, PALETTE),
+            wall_time(20, 0, 0),
+            &[20, 120],
+        );
+    }
+}
+
+#[test]
+fn effects() {
+    assert_frames(
+        "plasma",
+        PlasmaAnimation::new(PALETTE, 1.0),
+        wall_time(20, 0, 0),
+        &[0, 40],
+    );
+    assert_frames(
+        "plasma_halloween",
+        PlasmaAnimation::new(Palette::Halloween, 2.0),
+        wall_time(20, 0, 0),
+        &[40],
+    );
+    assert_frames(
+        "fire",
+        FireAnimation::new(PALETTE, 1.0),
+        wall_time(20, 0, 0),
+        &[60],
+    );
+    assert_frames(
+        "fire_neon",
+        FireAnimation::new(Palette::Neon, 1.0),
+        wall_time(20, 0, 0),
+        &[60],
+    );
+    assert_frames(
+        "starfield",
+        StarfieldAnimation::new(PALETTE, 1.0),
+        wall_time(20, 0, 0),
+        &[0, 60],
+    );
+    assert_frames(
+        "life",
+        LifeAnimation::new(PALETTE, 1.0),
+        wall_time(20, 0, 0),
+        &[0, 20],
+    );
+}
+
+#[test]
+fn spectrum() {
+    // A bass note, a voice and a hi-hat
+    let chord = AudioBuffer::new(SAMPLE_RATE);
+    chord.push(
+        sine(80.0, 0.5, Duration::from_millis(50))
+            .zip(sine(880.0, 0.2, Duration::from_millis(50)))
+            .zip(sine(6000.0, 0.05, Duration::from_millis(50)))
+            .map(|((a, b), c)| a + b + c),
+    );
+
+    assert_frames(
+        "spectrum_bars",
+        SpectrumAnimation::new(chord.clone(), SpectrumMode::Bars, PALETTE),
+        wall_time(20, 0, 0),
+        &[0],
+    );
+    assert_frames(
+        "spectrum_meter",
+        SpectrumAnimation::new(chord, SpectrumMode::Meter, PALETTE),
+        wall_time(20, 0, 0),
+        &[0],
+    );
+
+    // Bars fall back slowly once it gets quiet
+    let clock = FakeClock::new(wall_time(20, 0, 0));
+    let mut ctx = AnimationContext::with_clock(clock.clone(), SEED);
+
+    let audio = AudioBuffer::new(SAMPLE_RATE);
+    audio.push(sine(440.0, 0.8, Duration::from_millis(50)));
+
+    let mut animation = SpectrumAnimation::new(audio.clone(), SpectrumMode::Bars, PALETTE);
+    step_animation(&mut animation, &mut ctx, &clock, TICK, TIMEOUT).unwrap();
+
+    audio.push(sine(0.0, 0.0, Duration::from_millis(50)));
+
+    for _ in 0..10 {
+        step_animation(&mut animation, &mut ctx, &clock, TICK, TIMEOUT).unwrap();
+    }
+
+    let frame = step_animation(&mut animation, &mut ctx, &clock, TICK, TIMEOUT).unwrap();
+    assert_golden("spectrum_bars_falling", &frame);
+}
+
+#[test]
+fn spectrum_pulse() {
+    let clock = FakeClock::new(wall_time(20, 0, 0));
+    let mut ctx = AnimationContext::with_clock(clock.clone(), SEED);
+
+    let audio = AudioBuffer::new(SAMPLE_RATE);
+    let mut animation = SpectrumAnimation::new(audio.clone(), SpectrumMode::Pulse, PALETTE);
+
+    // A kick drum every 20 frames, which is 120 beats per minute at 25ms per frame
+    for index in 0..=45 {
+        let kick = index % 20 < 2;
+        audio.push(sine(
+            60.0,
+            if kick { 0.9 } else { 0.0 },
+            Duration::from_millis(25),
+        ));
+
+        let frame = step_animation(&mut animation, &mut ctx, &clock, TICK, TIMEOUT).unwrap();
+
+        // On a beat, fading out, and the next beat in the next color
+        if [0, 10, 20].contains(&index) {
+            assert_golden(&format!("spectrum_pulse_{index}"), &frame);
+        }
+    }
+}
+
+#[test]
+fn talking_face() {
+    // The first two formants of each vowel, with silence for a closed mouth
+    let vowels = [
+        ("closed", 0.0, 0.0),
+        ("a", 750.0, 1150.0),
+        ("e", 400.0, 2100.0),
+        ("o", 500.0, 850.0),
+        ("u", 300.0, 750.0),
+    ];
+
+    for (name, f1, f2) in vowels {
+        let voice = AudioBuffer::new(SAMPLE_RATE);
+        let amplitude = if f1 > 0.0 { 0.3 } else { 0.0 };
+
+        voice.push(
+            sine(f1, amplitude, Duration::from_millis(50))
+                .zip(sine(f2, amplitude / 2.0, Duration::from_millis(50)))
+                .map(|(a, b)| a + b),
+        );
+
+        let animation = OverlayAnimation::new(
+            Box::new(EyesAnimation::new()),
+            Box::new(MouthAnimation::new(voice)),
+            Rgb([0, 0, 0]),
+        );
+
+        assert_frames(
+            &format!("talking_face_{name}"),
+            animation,
+            wall_time(20, 0, 0),
+            &[0],
+        );
+    }
+}
+
+#[test]
+fn aseprite() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("sprites/heart.aseprite");
+    let file = AsepriteFile::open(&path).unwrap();
+
+    let layers = file
+        .layers
+        .iter()
+        .map(|layer| (layer.name.as_str(), layer.visible, layer.opacity))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        layers,
+        [
+            ("Background", true, 128),
+            ("Guide", false, 255),
+            ("Heart", true, 255)
+        ]
+    );
+
+    let durations = file
+        .frames
+        .iter()
+        .map(|frame| frame.duration.as_millis())
+        .collect::<Vec<_>>();
+
+    assert_eq!(durations, [100, 150, 100, 200]);
+
+    // Ping-pong goes back and forth between the first and last frame of the tag
+    let beat = AsepriteAnimation::new(&file, Some("beat")).unwrap();
+    assert_frames("aseprite_beat", beat, wall_time(20, 0, 0), &[0, 1, 2, 3, 4]);
+
+    // Plays twice, then finishes
+    let mut fade = AsepriteAnimation::new(&file, Some("fade")).unwrap();
+    let clock = FakeClock::new(wall_time(20, 0, 0));
+    let mut ctx = AnimationContext::with_clock(clock.clone(), SEED);
+
+    for _ in 0..2 {
+        let frame = step_animation(&mut fade, &mut ctx, &clock, TICK, TIMEOUT).unwrap();
+        assert_golden("aseprite_fade", &frame);
+    }
+
+    assert!(step_animation(&mut fade, &mut ctx, &clock, TICK, TIMEOUT).is_none());
+    assert!(AsepriteAnimation::new(&file, Some("missing")).is_err());
+}
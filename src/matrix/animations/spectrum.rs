@@ -0,0 +1,319 @@
+use std::{collections::VecDeque, time::Duration};
+
+use embedded_graphics::{pixelcolor::Rgb888, prelude::RgbColor};
+use image::{Rgb, RgbImage};
+use serde::Deserialize;
+
+use crate::{
+    audio::{self, Analyzer, AudioBuffer},
+    matrix::color_utils::{interpolate_colors, Palette},
+};
+
+use super::{Animation, AnimationContext};
+
+const FRAME_TIME: Duration = Duration::from_millis(25);
+
+const FFT_SIZE: usize = 1024;
+
+// 16 bars of 3 pixels with a 1 pixel gap fill the panel exactly
+const BANDS: usize = 16;
+const BAR_WIDTH: u32 = 3;
+
+const MIN_FREQUENCY: f32 = 40.0;
+const MAX_FREQUENCY: f32 = 16000.0;
+
+// Levels below this are drawn as silence
+const FLOOR_DB: f32 = -60.0;
+
+// How far bars and peaks fall per frame, as part of the full height
+const FALL: f32 = 0.04;
+const PEAK_FALL: f32 = 0.01;
+
+// Peaks stay in place for this many frames before falling
+const PEAK_HOLD: u32 = 20;
+
+// The bass is everything below this frequency, which is where the beat is
+const BASS_FREQUENCY: f32 = 150.0;
+
+// About a second of bass energy at the frame rate
+const BEAT_HISTORY: usize = 40;
+
+// The bass has to be this much louder than its recent average to count as a beat
+const BEAT_THRESHOLD: f32 = 1.5;
+const MIN_BEAT_INTERVAL: Duration = Duration::from_millis(200);
+const PULSE_DECAY: f32 = 0.9;
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SpectrumMode {
+    /// Bars for each frequency band, with falling peaks
+    Bars,
+
+    /// A single loudness meter, green to red
+    Meter,
+
+    /// Fills the panel with the next color of the palette on every beat
+    Pulse,
+}
+
+impl SpectrumMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Bars => "Bars",
+            Self::Meter => "VU",
+            Self::Pulse => "Pulse",
+        }
+    }
+}
+
+/// Draws the music coming in through an [`AudioBuffer`]
+pub struct SpectrumAnimation {
+    audio: AudioBuffer,
+    mode: SpectrumMode,
+    colors: Vec<Rgb888>,
+
+    analyzer: Analyzer,
+
+    levels: [f32; BANDS],
+    peaks: [f32; BANDS],
+    peak_age: [u32; BANDS],
+
+    bass_history: VecDeque<f32>,
+    last_beat: Option<Duration>,
+    pulse: f32,
+    color_index: usize,
+
+    last_frame: Duration,
+}
+
+impl SpectrumAnimation {
+    pub fn new(audio: AudioBuffer, mode: SpectrumMode, palette: Palette) -> Self {
+        Self {
+            audio,
+            mode,
+            colors: palette.colors(),
+
+            analyzer: Analyzer::new(FFT_SIZE),
+
+            levels: [0.0; BANDS],
+            peaks: [0.0; BANDS],
+            peak_age: [0; BANDS],
+
+            bass_history: VecDeque::with_capacity(BEAT_HISTORY),
+            last_beat: None,
+            pulse: 0.0,
+            color_index: 0,
+
+            last_frame: Duration::ZERO,
+        }
+    }
+
+    /// Jumps up to a louder `level` right away, but falls back slowly
+    fn set_level(&mut self, index: usize, level: f32) {
+        self.levels[index] = level.max(self.levels[index] - FALL);
+
+        if level >= self.peaks[index] {
+            self.peaks[index] = level;
+            self.peak_age[index] = 0;
+        } else if self.peak_age[index] < PEAK_HOLD {
+            self.peak_age[index] += 1;
+        } else {
+            self.peaks[index] = (self.peaks[index] - PEAK_FALL).max(0.0);
+        }
+    }
+
+    fn update_bars(&mut self, spectrum: &[f32], sample_rate: u32) {
+        let bin_width = self.analyzer.bin_width(sample_rate);
+        let ratio = MAX_FREQUENCY / MIN_FREQUENCY;
+
+        for band in 0..BANDS {
+            // Bands are spaced logarithmically, like notes
+            let low = MIN_FREQUENCY * ratio.powf(band as f32 / BANDS as f32);
+            let high = MIN_FREQUENCY * ratio.powf((band + 1) as f32 / BANDS as f32);
+
+            let first = ((low / bin_width) as usize).clamp(1, spectrum.len() - 1);
+            let last = ((high / bin_width) as usize).clamp(first + 1, spectrum.len());
+
+            let magnitude = spectrum[first..last].iter().copied().fold(0.0, f32::max);
+            let level = (audio::to_db(magnitude) / -FLOOR_DB + 1.0).clamp(0.0, 1.0);
+
+            self.set_level(band, level);
+        }
+    }
+
+    fn update_meter(&mut self, samples: &[f32]) {
+        let level = (audio::to_db(audio::rms(samples)) / -FLOOR_DB + 1.0).clamp(0.0, 1.0);
+
+        self.set_level(0, level);
+    }
+
+    fn update_pulse(&mut self, ctx: &AnimationContext, spectrum: &[f32], sample_rate: u32) {
+        let bin_width = self.analyzer.bin_width(sample_rate);
+        let bass_bins = ((BASS_FREQUENCY / bin_width) as usize)
+            .max(2)
+            .min(spectrum.len());
+
+        let energy = spectrum[1..bass_bins].iter().map(|m| m * m).sum::<f32>();
+        let average = self.bass_history.iter().sum::<f32>() / self.bass_history.len().max(1) as f32;
+
+        let is_beat = energy > average * BEAT_THRESHOLD
+            && audio::to_db(energy.sqrt()) > FLOOR_DB
+            && self
+                .last_beat
+                .is_none_or(|last| ctx.elapsed(last) >= MIN_BEAT_INTERVAL);
+
+        if is_beat {
+            self.last_beat = Some(ctx.now());
+            self.pulse = 1.0;
+            self.color_index = (self.color_index + 1) % self.colors.len();
+        } else {
+            self.pulse *= PULSE_DECAY;
+        }
+
+        if self.bass_history.len() == BEAT_HISTORY {
+            self.bass_history.pop_front();
+        }
+
+        self.bass_history.push_back(energy);
+    }
+
+    fn draw_bars(&self) -> RgbImage {
+        let mut image = RgbImage::new(64, 32);
+
+        for band in 0..BANDS as u32 {
+            let height = (self.levels[band as usize] * 32.0).round() as u32;
+            let peak = ((self.peaks[band as usize] * 32.0).round() as u32).min(31);
+
+            for x in band * (BAR_WIDTH + 1)..band * (BAR_WIDTH + 1) + BAR_WIDTH {
+                for row in 0..height {
+                    let color = interpolate_colors(&self.colors, row as f32 / 31.0);
+
+                    image.put_pixel(x, 31 - row, Rgb([color.r(), color.g(), color.b()]));
+                }
+
+                if peak > 0 {
+                    image.put_pixel(x, 31 - peak, Rgb([255, 255, 255]));
+                }
+            }
+        }
+
+        image
+    }
+
+    fn draw_meter(&self) -> RgbImage {
+        let mut image = RgbImage::new(64, 32);
+
+        let width = (self.levels[0] * 64.0).round() as u32;
+        let peak = ((self.peaks[0] * 64.0).round() as u32).min(63);
+
+        for x in 0..64 {
+            // Leave a gap every 4 pixels, so the meter looks like it's made of segments
+            if x % 4 == 3 {
+                continue;
+            }
+
+            let lit = x < width || (x == peak && peak > 0);
+            let color = match x {
+                0..=43 => Rgb([0, 255, 0]),
+                44..=55 => Rgb([255, 200, 0]),
+                _ => Rgb([255, 0, 0]),
+            };
+
+            for y in 10..22 {
+                let pixel = if lit {
+                    color
+                } else {
+                    Rgb(color.0.map(|c| c / 12))
+                };
+
+                image.put_pixel(x, y, pixel);
+            }
+        }
+
+        image
+    }
+
+    fn draw_pulse(&self) -> RgbImage {
+        let color = self.colors[self.color_index];
+        let brightness = 0.1 + 0.9 * self.pulse;
+
+        RgbImage::from_fn(64, 32, |x, y| {
+            // Brightest in the center, so it looks like the pulse comes from there
+            let dx = (x as f32 - 31.5) / 32.0;
+            let dy = (y as f32 - 15.5) / 16.0;
+            let falloff = 1.0 - (dx * dx + dy * dy).sqrt().min(1.4) * 0.5;
+
+            let scale = brightness * falloff;
+
+            Rgb([
+                (color.r() as f32 * scale) as u8,
+                (color.g() as f32 * scale) as u8,
+                (color.b() as f32 * scale) as u8,
+            ])
+        })
+    }
+}
+
+impl Animation for SpectrumAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        ctx.elapsed(self.last_frame) > FRAME_TIME
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        let samples = self.audio.latest(FFT_SIZE);
+        let sample_rate = self.audio.sample_rate();
+
+        let image = match self.mode {
+            SpectrumMode::Bars => {
+                let spectrum = self.analyzer.magnitudes(&samples);
+                self.update_bars(&spectrum, sample_rate);
+                self.draw_bars()
+            }
+            SpectrumMode::Meter => {
+                self.update_meter(&samples);
+                self.draw_meter()
+            }
+            SpectrumMode::Pulse => {
+                let spectrum = self.analyzer.magnitudes(&samples);
+                self.update_pulse(ctx, &spectrum, sample_rate);
+                self.draw_pulse()
+            }
+        };
+
+        self.last_frame = ctx.now();
+
+        Some(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::OffsetDateTime;
+
+    use super::*;
+    use crate::matrix::animations::{step_animation, FakeClock};
+
+    #[test]
+    fn low_sample_rates() {
+        for sample_rate in [8, 100, 250] {
+            let clock = FakeClock::new(OffsetDateTime::UNIX_EPOCH);
+            let mut ctx = AnimationContext::with_clock(clock.clone(), 0);
+
+            let audio = AudioBuffer::new(sample_rate);
+            let mut animation =
+                SpectrumAnimation::new(audio.clone(), SpectrumMode::Pulse, Palette::default());
+
+            // Below about 300 Hz the bass goes past the highest frequency of the spectrum
+            audio.push((0..FFT_SIZE).map(|index| (index % 2) as f32));
+
+            let frame = step_animation(
+                &mut animation,
+                &mut ctx,
+                &clock,
+                Duration::from_millis(5),
+                Duration::from_secs(1),
+            );
+            assert!(frame.is_some(), "no frame at {sample_rate} Hz");
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Sprite sheets: one PNG with all frames of an asset, and a TOML manifest that names them
+//!
+//! ```toml
+//! # The PNG with all frames, relative to the manifest
+//! image = "eyes.png"
+//!
+//! [[frames]]
+//! name = "closed"
+//! x = 0
+//! y = 0
+//! width = 64
+//! height = 32
+//! # How long the frame is shown in milliseconds, defaults to 100
+//! duration = 100
+//! # How long an animation moves the frame in milliseconds, for frames that are tweened
+//! tween = 750
+//! ```
+
+use std::{fs, path::Path, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_DURATION: u64 = 100;
+
+#[derive(Deserialize, Serialize)]
+struct Manifest {
+    image: String,
+    frames: Vec<FrameEntry>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct FrameEntry {
+    name: String,
+
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+
+    #[serde(default = "default_duration")]
+    duration: u64,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tween: Option<u64>,
+}
+
+fn default_duration() -> u64 {
+    DEFAULT_DURATION
+}
+
+pub struct SheetFrame {
+    pub name: String,
+    pub image: RgbImage,
+    pub duration: Duration,
+    pub tween: Option<Duration>,
+}
+
+/// Named frames cut out of a sprite sheet, in the order of the manifest
+pub struct SpriteSheet {
+    frames: Vec<SheetFrame>,
+}
+
+impl SpriteSheet {
+    /// Reads the manifest at `path`, and the PNG it points to
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        let manifest = fs::read_to_string(path)
+            .with_context(|| format!("Cannot read sprite sheet {}", path.display()))?;
+        let manifest: Manifest = toml::from_str(&manifest)?;
+
+        let image_path = path.with_file_name(&manifest.image);
+        let image = image::open(&image_path)
+            .with_context(|| format!("Cannot read sprite sheet image {}", image_path.display()))?;
+
+        Self::cut(manifest, image.into_rgb8())
+    }
+
+    /// Parses a sheet that is embedded in the binary, the `image` of the manifest is ignored
+    pub fn parse(manifest: &str, png: &[u8]) -> Result<Self> {
+        let manifest: Manifest = toml::from_str(manifest)?;
+        let image = image::load_from_memory(png)?.into_rgb8();
+
+        Self::cut(manifest, image)
+    }
+
+    fn cut(manifest: Manifest, image: RgbImage) -> Result<Self> {
+        let mut frames: Vec<SheetFrame> = Vec::with_capacity(manifest.frames.len());
+
+        for entry in manifest.frames {
+            let fits = |start: u32, length: u32, size: u32| {
+                start.checked_add(length).is_some_and(|end| end <= size)
+            };
+
+            if !fits(entry.x, entry.width, image.width())
+                || !fits(entry.y, entry.height, image.height())
+            {
+                return Err(anyhow!(
+                    "Frame {} does not fit in the {}x{} sheet",
+                    entry.name,
+                    image.width(),
+                    image.height()
+                ));
+            }
+
+            if frames.iter().any(|frame| frame.name == entry.name) {
+                return Err(anyhow!("Frame {} is in the manifest twice", entry.name));
+            }
+
+            let frame =
+                image::imageops::crop_imm(&image, entry.x, entry.y, entry.width, entry.height);
+
+            frames.push(SheetFrame {
+                name: entry.name,
+                image: frame.to_image(),
+                duration: Duration::from_millis(entry.duration),
+                tween: entry.tween.map(Duration::from_millis),
+            });
+        }
+
+        Ok(Self { frames })
+    }
+
+    pub fn frames(&self) -> &[SheetFrame] {
+        &self.frames
+    }
+
+    pub fn frame(&self, name: &str) -> Option<&SheetFrame> {
+        self.frames.iter().find(|frame| frame.name == name)
+    }
+
+    /// The image of frame `name`, for sheets that are known to have it
+    pub fn image(&self, name: &str) -> &RgbImage {
+        match self.frame(name) {
+            Some(frame) => &frame.image,
+            None => panic!("Sprite sheet has no frame named {name}"),
+        }
+    }
+}
+
+/// Writes a manifest for the PNG at `path`, which has frames of `width` by `height` in a grid
+///
+/// Frames are read left to right and top to bottom. They get the given `names`, or are numbered
+/// when there are no names left. The manifest is written next to the PNG, with a `.toml`
+/// extension.
+pub fn convert_png(
+    path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    duration: Duration,
+    names: &[String],
+) -> Result<()> {
+    let path = path.as_ref();
+    let image = image::open(path)?;
+
+    if width == 0 || height == 0 || image.width() % width != 0 || image.height() % height != 0 {
+        return Err(anyhow!(
+            "A {}x{} image cannot be split into frames of {width}x{height}",
+            image.width(),
+            image.height()
+        ));
+    }
+
+    let (columns, rows) = (image.width() / width, image.height() / height);
+
+    if names.len() > (columns * rows) as usize {
+        return Err(anyhow!(
+            "Got {} names for only {} frames",
+            names.len(),
+            columns * rows
+        ));
+    }
+
+    let frames = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .enumerate()
+        .map(|(index, (column, row))| FrameEntry {
+            name: names
+                .get(index)
+                .cloned()
+                .unwrap_or_else(|| format!("frame_{index}")),
+
+            x: column * width,
+            y: row * height,
+            width,
+            height,
+
+            duration: duration.as_millis() as u64,
+            tween: None,
+        })
+        .collect();
+
+    let manifest = Manifest {
+        image: path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or(anyhow!("Invalid image path"))?
+            .to_string(),
+        frames,
+    };
+
+    fs::write(path.with_extension("toml"), toml::to_string(&manifest)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use super::*;
+
+    #[test]
+    fn sprite_sheet_converter() {
+        let images = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("images");
+        let dir = env::temp_dir().join(format!("feestje-sheet-{}", std::process::id()));
+
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy(images.join("eyes.png"), dir.join("eyes.png")).unwrap();
+
+        let names = ["closed", "center", "apart"].map(String::from);
+        convert_png(
+            dir.join("eyes.png"),
+            64,
+            32,
+            Duration::from_millis(100),
+            &names,
+        )
+        .unwrap();
+
+        let converted = SpriteSheet::open(dir.join("eyes.toml")).unwrap();
+        let original = SpriteSheet::open(images.join("eyes.toml")).unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Frames without a name are numbered
+        let names = converted
+            .frames()
+            .iter()
+            .map(|frame| frame.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names[..6],
+            ["closed", "center", "apart", "frame_3", "frame_4", "frame_5"]
+        );
+        assert_eq!(names.len(), original.frames().len());
+
+        for (converted, original) in converted.frames().iter().zip(original.frames()) {
+            assert!(converted.image == original.image);
+            assert_eq!(converted.duration, original.duration);
+            assert_eq!(converted.tween, original.tween);
+        }
+    }
+
+    #[test]
+    fn sprite_sheet_bounds() {
+        let png =
+            fs::read(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("images/eyes.png")).unwrap();
+
+        // A frame that reaches past u32::MAX must not wrap around into the sheet
+        let manifest = r#"
+            image = "eyes.png"
+
+            [[frames]]
+            name = "wrapped"
+            x = 4294967295
+            y = 0
+            width = 2
+            height = 32
+        "#;
+
+        assert!(SpriteSheet::parse(manifest, &png).is_err());
+    }
+}
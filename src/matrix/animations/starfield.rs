@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use image::{Rgb, RgbImage};
+use rand::Rng;
+
+use crate::matrix::color_utils::Palette;
+
+use super::{Animation, AnimationContext};
+
+const STAR_COUNT: usize = 120;
+
+// Depth at which new stars appear, and at which they're closest before being recycled
+const FAR: f32 = 4.0;
+const NEAR: f32 = 0.1;
+
+// Depth units per second at normal speed
+const FLY_SPEED: f32 = 1.5;
+
+struct Star {
+    x: f32,
+    y: f32,
+    z: f32,
+    color: Rgb<u8>,
+}
+
+/// Flying through a field of stars, that get brighter as they come closer
+///
+/// Random palettes have white stars, other palettes color the stars with their own colors.
+pub struct StarfieldAnimation {
+    palette: Palette,
+    speed: f32,
+
+    stars: Vec<Star>,
+    last_frame: Duration,
+}
+
+impl StarfieldAnimation {
+    pub fn new(palette: Palette, speed: f32) -> Self {
+        Self {
+            palette,
+            speed,
+
+            // Stars are placed on the first frame, using the context's randomness
+            stars: vec![],
+            last_frame: Duration::ZERO,
+        }
+    }
+
+    fn star<R: Rng>(&self, rng: &mut R, z: f32) -> Star {
+        let color = match self.palette {
+            Palette::Random(_) => Rgb([255, 255, 255]),
+            _ => self.palette.pick(rng),
+        };
+
+        Star {
+            x: rng.gen_range(-2.0..2.0),
+            y: rng.gen_range(-1.0..1.0),
+            z,
+            color,
+        }
+    }
+}
+
+impl Animation for StarfieldAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        // Target: 40 FPS
+        ctx.elapsed(self.last_frame) > Duration::from_millis(25)
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        let dt = if self.stars.is_empty() {
+            // Spread the first stars over the full depth, instead of starting with a wall of them
+            for _ in 0..STAR_COUNT {
+                let z = ctx.rng.gen_range(NEAR..FAR);
+                let star = self.star(&mut ctx.rng, z);
+
+                self.stars.push(star);
+            }
+
+            0.0
+        } else {
+            ctx.elapsed(self.last_frame).as_secs_f32()
+        };
+
+        let mut image = RgbImage::new(64, 32);
+
+        for i in 0..self.stars.len() {
+            self.stars[i].z -= dt * FLY_SPEED * self.speed;
+
+            let star = &self.stars[i];
+            let (sx, sy) = (32.0 + star.x / star.z * 16.0, 16.0 + star.y / star.z * 16.0);
+
+            if star.z <= NEAR || !(0.0..64.0).contains(&sx) || !(0.0..32.0).contains(&sy) {
+                self.stars[i] = self.star(&mut ctx.rng, FAR);
+                continue;
+            }
+
+            let brightness = 1.0 - (star.z - NEAR) / (FAR - NEAR);
+            let color = Rgb(star.color.0.map(|c| (c as f32 * brightness) as u8));
+
+            image.put_pixel(sx as u32, sy as u32, color);
+        }
+
+        self.last_frame = ctx.now();
+
+        Some(image)
+    }
+
+    fn reload(&mut self, ctx: &mut AnimationContext) {
+        self.palette.reseed(&mut ctx.rng);
+    }
+}
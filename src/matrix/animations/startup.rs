@@ -1,67 +1,64 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
 use image::RgbImage;
 
-use crate::image;
+use super::{
+    tween::{Easing, Sprite, Track},
+    Animation, AnimationContext, SpriteSheet,
+};
 
-use super::Animation;
-
-const IMG_FERRIS: &[u8] = include_bytes!("../../../images/ferris.bmp");
+const FERRIS_MANIFEST: &str = include_str!("../../../images/ferris.toml");
+const FERRIS_SHEET: &[u8] = include_bytes!("../../../images/ferris.png");
 
 pub struct StartupAnimation {
-    image: RgbImage,
+    ferris: Sprite,
 
-    last_frame: Instant,
-    frame: u32,
+    start: Option<Duration>,
+    last_frame: Duration,
+    finished: bool,
 }
 
 impl StartupAnimation {
     pub fn load() -> Result<Self> {
-        let image = RgbImage::from_raw(30, 21, image::decode_bmp(IMG_FERRIS)?)
-            .ok_or(anyhow!("Buffer has wrong length"))?;
+        let sheet = SpriteSheet::parse(FERRIS_MANIFEST, FERRIS_SHEET)?;
+        let frame = sheet
+            .frame("ferris")
+            .ok_or(anyhow!("Ferris sprite sheet has no ferris"))?;
+        let reveal = frame
+            .tween
+            .ok_or(anyhow!("Ferris sprite sheet has no reveal time"))?;
+
+        // Ferris is revealed from left to right over the tween time of the frame
+        let ferris = Sprite::new(frame.image.clone())
+            .position(Track::new((17.0, 5.0)))
+            .reveal(Track::new(0.0).to(reveal, 1.0, Easing::Linear));
 
         Ok(Self {
-            image,
+            ferris,
 
-            last_frame: Instant::now(),
-            frame: 0,
+            start: None,
+            last_frame: Duration::ZERO,
+            finished: false,
         })
     }
 }
 
 impl Animation for StartupAnimation {
-    fn should_execute(&self) -> bool {
-        self.frame <= 30 && self.last_frame.elapsed() > Duration::from_millis(25)
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        !self.finished && ctx.elapsed(self.last_frame) > Duration::from_millis(25)
     }
 
-    fn next_frame(&mut self) -> Option<RgbImage> {
-        let mut result = RgbImage::new(64, 32);
-
-        match self.frame {
-            0..=30 => {
-                let width = self.frame;
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        let start = *self.start.get_or_insert(ctx.now());
+        let t = ctx.elapsed(start);
 
-                for x in 0..width {
-                    for y in 0..21 {
-                        let pixel = self.image.get_pixel(x, y);
-                        result.put_pixel(x + 17, y + 5, *pixel);
-                    }
-                }
-            }
-
-            _ => {
-                for x in 0..30 {
-                    for y in 0..21 {
-                        let pixel = self.image.get_pixel(x, y);
-                        result.put_pixel(x + 17, y + 5, *pixel);
-                    }
-                }
-            }
-        }
+        let mut result = RgbImage::new(64, 32);
+        self.ferris.draw(&mut result, t);
 
-        self.last_frame = Instant::now();
-        self.frame += 1;
+        // Stay on the last frame once Ferris is completely visible
+        self.finished = t >= self.ferris.duration();
+        self.last_frame = ctx.now();
 
         Some(result)
     }
@@ -1,4 +1,4 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use embedded_graphics::{
     pixelcolor::Rgb888,
@@ -8,7 +8,7 @@ use embedded_graphics::{
 };
 use image::RgbImage;
 
-use super::{Animation, ImageBuffer};
+use super::{Animation, AnimationContext, ImageBuffer};
 
 const DRAW_MASK: &[u8] = &[
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -116,7 +116,7 @@ pub enum Winner {
 pub struct TicTacToeAnimation {
     winner: Winner,
 
-    last_frame: Instant,
+    last_frame: Duration,
     frame: u32,
 }
 
@@ -125,19 +125,19 @@ impl TicTacToeAnimation {
         Self {
             winner,
 
-            last_frame: Instant::now(),
+            last_frame: Duration::ZERO,
             frame: 0,
         }
     }
 }
 
 impl Animation for TicTacToeAnimation {
-    fn should_execute(&self) -> bool {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
         // Target: 40 FPS
-        self.last_frame.elapsed() > Duration::from_millis(25)
+        ctx.elapsed(self.last_frame) > Duration::from_millis(25)
     }
 
-    fn next_frame(&mut self) -> Option<RgbImage> {
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
         let mut result = ImageBuffer::new();
 
         let pixel_fn = |(point, color)| Pixel(point, color);
@@ -244,7 +244,7 @@ impl Animation for TicTacToeAnimation {
         }
 
         self.frame += 1;
-        self.last_frame = Instant::now();
+        self.last_frame = ctx.now();
 
         Some(result.into_image())
     }
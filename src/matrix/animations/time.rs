@@ -1,20 +1,26 @@
-use super::{Animation, ImageBuffer};
+use super::{Animation, AnimationContext, ImageBuffer};
 use crate::{
     image,
-    matrix::{self, iter::IterLooping},
+    matrix::{self, color_utils::Palette, iter::IterLooping},
 };
 use embedded_graphics::{
     image::ImageRaw,
-    mono_font::{mapping::StrGlyphMapping, DecorationDimensions, MonoFont, MonoTextStyle},
+    mono_font::{
+        ascii::{FONT_5X7, FONT_9X15},
+        mapping::StrGlyphMapping,
+        DecorationDimensions, MonoFont, MonoTextStyle,
+    },
     pixelcolor::Rgb888,
     prelude::*,
-    primitives::Rectangle,
+    primitives::{Circle, Line, PrimitiveStyle, Rectangle},
     text::{Alignment, Baseline, Text, TextStyle, TextStyleBuilder},
 };
 use image::RgbImage;
-use std::time::{Duration, Instant};
+use serde::Deserialize;
+use std::{f32::consts::PI, time::Duration};
+use time::OffsetDateTime;
 
-const SEVENT_SEGMENT_FONT: MonoFont = MonoFont {
+pub(super) const SEVENT_SEGMENT_FONT: MonoFont = MonoFont {
     image: ImageRaw::new(include_bytes!("../../../assets/font.raw"), 120),
     glyph_mapping: &StrGlyphMapping::new("0123456789 :", 0),
     character_size: Size::new(10, 18),
@@ -24,8 +30,8 @@ const SEVENT_SEGMENT_FONT: MonoFont = MonoFont {
     strikethrough: DecorationDimensions::default_strikethrough(18),
 };
 
-const TEXT_POSITION: Point = Point::new(31, 24);
-const TEXT_STYLE: TextStyle = TextStyleBuilder::new()
+pub(super) const TEXT_POSITION: Point = Point::new(31, 24);
+pub(super) const TEXT_STYLE: TextStyle = TextStyleBuilder::new()
     .baseline(Baseline::Bottom)
     .alignment(Alignment::Center)
     .build();
@@ -33,32 +39,96 @@ const TEXT_STYLE: TextStyle = TextStyleBuilder::new()
 const CHARACTER_STYLE: MonoTextStyle<'_, Rgb888> =
     MonoTextStyle::new(&SEVENT_SEGMENT_FONT, Rgb888::WHITE);
 
+const DATE_TIME_POSITION: Point = Point::new(31, 3);
+const DATE_POSITION: Point = Point::new(31, 20);
+const DATE_TEXT_STYLE: TextStyle = TextStyleBuilder::new()
+    .baseline(Baseline::Top)
+    .alignment(Alignment::Center)
+    .build();
+
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+const ANALOG_CENTER: Point = Point::new(31, 15);
+const ANALOG_RADIUS: f32 = 13.0;
+
+const BINARY_ON: Rgb888 = Rgb888::WHITE;
+const BINARY_OFF: Rgb888 = Rgb888::new(40, 40, 40);
+
+// Amount of pixels in the outer border ring, the inner ring has 8 less
+const BORDER_LENGTH: usize = 188;
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClockFace {
+    /// Only the animated border, no clock
+    Hidden,
+
+    /// Seven segment HH:MM
+    Digital,
+
+    /// Clock with hour, minute and second hands
+    Analog,
+
+    /// Binary coded decimal columns for HH MM SS
+    Binary,
+
+    /// Smaller HH:MM with the date underneath
+    DateTime,
+
+    /// Seven segment HH:MM, with the border filling up as the minute progresses
+    Progress,
+}
+
+impl ClockFace {
+    /// The face that comes after this one when cycling through them on the deck
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Digital => Self::Analog,
+            Self::Analog => Self::Binary,
+            Self::Binary => Self::DateTime,
+            Self::DateTime => Self::Progress,
+            Self::Progress => Self::Hidden,
+            Self::Hidden => Self::Digital,
+        }
+    }
+
+    fn shows_seconds(&self) -> bool {
+        matches!(self, Self::Analog | Self::Binary)
+    }
+}
+
 pub struct TimeAnimation {
-    last_frame: Instant,
+    last_frame: Duration,
     frame: usize,
 
-    disable_clock: bool,
+    face: ClockFace,
     last_hour: u8,
     last_minute: u8,
+    last_second: u8,
     last_colon: bool,
 
+    palette: Palette,
     table: Vec<Rgb888>,
     last_image: Option<ImageBuffer>,
 }
 
 impl TimeAnimation {
-    pub fn new(disable_clock: bool) -> Self {
-        let table = matrix::color_utils::generate_table();
+    pub fn new(face: ClockFace, palette: Palette) -> Self {
+        let table = matrix::color_utils::generate_table(&palette);
 
         Self {
-            last_frame: Instant::now(),
+            last_frame: Duration::ZERO,
             frame: 0,
 
-            disable_clock,
+            face,
             last_hour: 0,
             last_minute: 0,
+            last_second: 0,
             last_colon: true,
 
+            palette,
             table,
             last_image: None,
         }
@@ -66,11 +136,11 @@ impl TimeAnimation {
 }
 
 impl Animation for TimeAnimation {
-    fn should_execute(&self) -> bool {
-        self.last_frame.elapsed() > Duration::from_millis(10)
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        ctx.elapsed(self.last_frame) > Duration::from_millis(10)
     }
 
-    fn next_frame(&mut self) -> Option<RgbImage> {
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
         let mut result = self
             .last_image
             .clone()
@@ -176,24 +246,49 @@ impl Animation for TimeAnimation {
             )
             .ok()?;
 
-        if self.disable_clock {
+        if self.face == ClockFace::Hidden {
             self.frame = (self.frame + 1) % 190;
 
             let image = RgbImage::from_raw(64, 32, result.0.clone())?;
             return Some(image);
         }
 
-        // Render time
+        let local = local_time(ctx.wall_time());
+
+        if self.face == ClockFace::Progress {
+            // Blank out the part of the border that the current minute hasn't reached yet
+            let progress = (local.second() as f32 + local.millisecond() as f32 / 1000.0) / 60.0;
 
-        let local = time::OffsetDateTime::now_utc();
-        let (h, m, ms) = (local.hour(), local.minute(), local.millisecond());
+            for (inset, length) in [(0, BORDER_LENGTH), (1, BORDER_LENGTH - 8)] {
+                let lit = (length as f32 * progress) as usize;
 
-        // Timezone shenanigans
-        let h = if (1..10).contains(&h) { h + 1 } else { h + 2 } % 24;
+                result
+                    .draw_iter(
+                        border_points(inset)
+                            .skip(lit)
+                            .map(|pos| Pixel(pos, Rgb888::BLACK)),
+                    )
+                    .ok()?;
+            }
+        }
+
+        // Render time
+
+        let (h, m, s, ms) = (
+            local.hour(),
+            local.minute(),
+            local.second(),
+            local.millisecond(),
+        );
 
         let colon = ms > 500;
+        let s = if self.face.shows_seconds() { s } else { 0 };
 
-        if h == self.last_hour && m == self.last_minute && colon == self.last_colon {
+        if h == self.last_hour
+            && m == self.last_minute
+            && s == self.last_second
+            && colon == self.last_colon
+        {
             self.frame = (self.frame + 1) % 190;
 
             let image = RgbImage::from_raw(64, 32, result.0.clone())?;
@@ -202,6 +297,7 @@ impl Animation for TimeAnimation {
 
         self.last_hour = h;
         self.last_minute = m;
+        self.last_second = s;
         self.last_colon = colon;
 
         let area = Rectangle::new(Point::new(2, 2), Size::new(60, 28));
@@ -209,14 +305,13 @@ impl Animation for TimeAnimation {
             .draw_iter(area.points().map(|pos| Pixel(pos, Rgb888::BLACK)))
             .ok()?;
 
-        Text::with_text_style(
-            &format!("{h:0>2}{}{m:0>2}", if colon { ":" } else { " " }),
-            TEXT_POSITION,
-            CHARACTER_STYLE,
-            TEXT_STYLE,
-        )
-        .draw(&mut result)
-        .ok()?;
+        match self.face {
+            ClockFace::Digital | ClockFace::Progress => draw_digital(&mut result, h, m, colon)?,
+            ClockFace::Analog => draw_analog(&mut result, h, m, s)?,
+            ClockFace::Binary => draw_binary(&mut result, h, m, s)?,
+            ClockFace::DateTime => draw_date_time(&mut result, &local, colon)?,
+            ClockFace::Hidden => {}
+        }
 
         self.frame = (self.frame + 1) % 190;
 
@@ -226,7 +321,150 @@ impl Animation for TimeAnimation {
         Some(image)
     }
 
-    fn reload(&mut self) {
-        self.table = matrix::color_utils::generate_table();
+    fn reload(&mut self, ctx: &mut AnimationContext) {
+        self.palette.reseed(&mut ctx.rng);
+        self.table = matrix::color_utils::generate_table(&self.palette);
     }
 }
+
+/// The time here, for `utc`
+pub fn local_time(utc: OffsetDateTime) -> OffsetDateTime {
+    // Timezone shenanigans
+    let offset = if (1..10).contains(&utc.hour()) { 1 } else { 2 };
+
+    utc + time::Duration::hours(offset)
+}
+
+/// Walks a border ring clockwise, starting at the top left corner
+///
+/// `inset` 0 is the outermost ring of the panel, 1 the ring inside of that
+fn border_points(inset: i32) -> impl Iterator<Item = Point> {
+    let (left, top, right, bottom) = (inset, inset, 63 - inset, 31 - inset);
+
+    let top_edge = (left..=right).map(move |x| Point::new(x, top));
+    let right_edge = (top + 1..=bottom).map(move |y| Point::new(right, y));
+    let bottom_edge = (left..right).rev().map(move |x| Point::new(x, bottom));
+    let left_edge = (top + 1..bottom).rev().map(move |y| Point::new(left, y));
+
+    top_edge
+        .chain(right_edge)
+        .chain(bottom_edge)
+        .chain(left_edge)
+}
+
+fn draw_digital(result: &mut ImageBuffer, h: u8, m: u8, colon: bool) -> Option<()> {
+    Text::with_text_style(
+        &format!("{h:0>2}{}{m:0>2}", if colon { ":" } else { " " }),
+        TEXT_POSITION,
+        CHARACTER_STYLE,
+        TEXT_STYLE,
+    )
+    .draw(result)
+    .ok()?;
+
+    Some(())
+}
+
+fn draw_analog(result: &mut ImageBuffer, h: u8, m: u8, s: u8) -> Option<()> {
+    let hand = |fraction: f32, length: f32| {
+        let angle = fraction * 2.0 * PI;
+
+        Line::new(
+            ANALOG_CENTER,
+            ANALOG_CENTER
+                + Point::new(
+                    (angle.sin() * length).round() as i32,
+                    -(angle.cos() * length).round() as i32,
+                ),
+        )
+    };
+
+    Circle::with_center(ANALOG_CENTER, ANALOG_RADIUS as u32 * 2 + 1)
+        .into_styled(PrimitiveStyle::with_stroke(Rgb888::CSS_DIM_GRAY, 1))
+        .draw(result)
+        .ok()?;
+
+    // Hour markers
+    for hour in 0..12 {
+        let angle = hour as f32 / 12.0 * 2.0 * PI;
+        let pos = ANALOG_CENTER
+            + Point::new(
+                (angle.sin() * (ANALOG_RADIUS - 2.0)).round() as i32,
+                -(angle.cos() * (ANALOG_RADIUS - 2.0)).round() as i32,
+            );
+
+        Pixel(pos, Rgb888::WHITE).draw(result).ok()?;
+    }
+
+    let hours = (h % 12) as f32 + m as f32 / 60.0;
+    let minutes = m as f32 + s as f32 / 60.0;
+
+    hand(hours / 12.0, 6.0)
+        .into_styled(PrimitiveStyle::with_stroke(Rgb888::WHITE, 1))
+        .draw(result)
+        .ok()?;
+    hand(minutes / 60.0, 10.0)
+        .into_styled(PrimitiveStyle::with_stroke(Rgb888::WHITE, 1))
+        .draw(result)
+        .ok()?;
+    hand(s as f32 / 60.0, 11.0)
+        .into_styled(PrimitiveStyle::with_stroke(Rgb888::RED, 1))
+        .draw(result)
+        .ok()?;
+
+    Some(())
+}
+
+fn draw_binary(result: &mut ImageBuffer, h: u8, m: u8, s: u8) -> Option<()> {
+    let digits = [h / 10, h % 10, m / 10, m % 10, s / 10, s % 10];
+
+    for (column, digit) in digits.iter().enumerate() {
+        // Pairs of columns are grouped together, with some extra space between the groups
+        let x = 7 + column as i32 * 8 + (column as i32 / 2) * 2;
+
+        for bit in 0..4 {
+            let y = 24 - bit * 6;
+            let color = if digit & (1 << bit) > 0 {
+                BINARY_ON
+            } else {
+                BINARY_OFF
+            };
+
+            Rectangle::new(Point::new(x, y), Size::new(4, 4))
+                .into_styled(PrimitiveStyle::with_fill(color))
+                .draw(result)
+                .ok()?;
+        }
+    }
+
+    Some(())
+}
+
+fn draw_date_time(result: &mut ImageBuffer, local: &OffsetDateTime, colon: bool) -> Option<()> {
+    let (h, m) = (local.hour(), local.minute());
+
+    Text::with_text_style(
+        &format!("{h:0>2}{}{m:0>2}", if colon { ":" } else { " " }),
+        DATE_TIME_POSITION,
+        MonoTextStyle::new(&FONT_9X15, Rgb888::WHITE),
+        DATE_TEXT_STYLE,
+    )
+    .draw(result)
+    .ok()?;
+
+    Text::with_text_style(
+        &format!(
+            "{} {} {}",
+            local.day(),
+            MONTHS[u8::from(local.month()) as usize - 1],
+            local.year()
+        ),
+        DATE_POSITION,
+        MonoTextStyle::new(&FONT_5X7, Rgb888::CSS_LIGHT_GRAY),
+        DATE_TEXT_STYLE,
+    )
+    .draw(result)
+    .ok()?;
+
+    Some(())
+}
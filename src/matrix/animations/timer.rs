@@ -0,0 +1,246 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use embedded_graphics::{
+    mono_font::MonoTextStyle,
+    pixelcolor::Rgb888,
+    prelude::{RgbColor, WebColors},
+    text::Text,
+    Drawable,
+};
+use image::RgbImage;
+use time::OffsetDateTime;
+
+use super::{
+    time::{SEVENT_SEGMENT_FONT, TEXT_POSITION, TEXT_STYLE},
+    Animation, AnimationContext, ImageBuffer,
+};
+
+// The last seconds of a countdown are drawn in red
+const FINAL_SECONDS: Duration = Duration::from_secs(10);
+
+struct TimerState {
+    elapsed: Duration,
+    running: bool,
+}
+
+/// Shared start/pause/reset controls for the countdown and stopwatch animations
+///
+/// The animation lives on the matrix thread, so the deck keeps a clone of this to control it.
+/// Time is only added by the animation, using the time of its [`AnimationContext`].
+#[derive(Clone)]
+pub struct Timer(Arc<Mutex<TimerState>>);
+
+impl Timer {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(TimerState {
+            elapsed: Duration::ZERO,
+            running: false,
+        })))
+    }
+
+    pub fn start(&self) {
+        self.0.lock().expect("timer lock poisoned").running = true;
+    }
+
+    pub fn pause(&self) {
+        self.0.lock().expect("timer lock poisoned").running = false;
+    }
+
+    pub fn reset(&self) {
+        let mut state = self.0.lock().expect("timer lock poisoned");
+
+        state.elapsed = Duration::ZERO;
+        state.running = false;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.0.lock().expect("timer lock poisoned").running
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.0.lock().expect("timer lock poisoned").elapsed
+    }
+
+    /// Adds `delta` to the elapsed time, if the timer is running
+    fn tick(&self, delta: Duration) {
+        let mut state = self.0.lock().expect("timer lock poisoned");
+
+        if state.running {
+            state.elapsed += delta;
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where a countdown ends
+#[derive(Clone, Copy)]
+pub enum Deadline {
+    /// When the timer has run for this long
+    After(Duration),
+
+    /// At this wall-clock time, the countdown doesn't stop while the timer is paused
+    At(OffsetDateTime),
+}
+
+pub struct CountdownAnimation {
+    timer: Timer,
+    target: Deadline,
+
+    finale: Box<dyn Animation + Send + Sync>,
+    finale_started: bool,
+
+    last_frame: Option<Duration>,
+}
+
+impl CountdownAnimation {
+    /// Counts down to `target`, and plays `finale` once it has been reached
+    pub fn new(timer: Timer, target: Deadline, finale: Box<dyn Animation + Send + Sync>) -> Self {
+        Self {
+            timer,
+            target,
+
+            finale,
+            finale_started: false,
+
+            last_frame: None,
+        }
+    }
+
+    fn remaining(&self, ctx: &AnimationContext) -> Duration {
+        match self.target {
+            Deadline::After(duration) => duration.saturating_sub(self.timer.elapsed()),
+            Deadline::At(at) => (at - ctx.wall_time()).try_into().unwrap_or(Duration::ZERO),
+        }
+    }
+}
+
+impl Animation for CountdownAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        if self.remaining(ctx).is_zero() {
+            return self.finale.should_execute(ctx);
+        }
+
+        // Target: 20 FPS
+        should_tick(self.last_frame, ctx)
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        tick(&self.timer, &mut self.last_frame, ctx);
+
+        let remaining = self.remaining(ctx);
+
+        if remaining.is_zero() {
+            if !self.finale_started {
+                self.finale_started = true;
+                self.finale.reload(ctx);
+            }
+
+            return self.finale.next_frame(ctx);
+        }
+
+        // Timer got reset after the finale
+        self.finale_started = false;
+
+        // Round up, so that 00:00 is only shown when the countdown has actually finished
+        let color = if remaining <= FINAL_SECONDS {
+            Rgb888::RED
+        } else {
+            Rgb888::WHITE
+        };
+
+        let image = render_digits(
+            remaining + Duration::from_millis(999),
+            color,
+            self.timer.is_running(),
+        );
+
+        Some(image)
+    }
+}
+
+pub struct StopwatchAnimation {
+    timer: Timer,
+
+    last_frame: Option<Duration>,
+}
+
+impl StopwatchAnimation {
+    pub fn new(timer: Timer) -> Self {
+        Self {
+            timer,
+
+            last_frame: None,
+        }
+    }
+}
+
+impl Animation for StopwatchAnimation {
+    fn should_execute(&self, ctx: &AnimationContext) -> bool {
+        // Target: 20 FPS
+        should_tick(self.last_frame, ctx)
+    }
+
+    fn next_frame(&mut self, ctx: &mut AnimationContext) -> Option<RgbImage> {
+        tick(&self.timer, &mut self.last_frame, ctx);
+
+        let color = if self.timer.is_running() {
+            Rgb888::WHITE
+        } else {
+            Rgb888::CSS_GRAY
+        };
+
+        let image = render_digits(self.timer.elapsed(), color, true);
+
+        Some(image)
+    }
+}
+
+fn should_tick(last_frame: Option<Duration>, ctx: &AnimationContext) -> bool {
+    last_frame.is_none_or(|last_frame| ctx.elapsed(last_frame) > Duration::from_millis(50))
+}
+
+/// Moves the timer forward by the time that has passed since the previous frame
+fn tick(timer: &Timer, last_frame: &mut Option<Duration>, ctx: &AnimationContext) {
+    let now = ctx.now();
+
+    if let Some(last_frame) = last_frame {
+        timer.tick(now.saturating_sub(*last_frame));
+    }
+
+    *last_frame = Some(now);
+}
+
+/// Draws MM:SS, or HH:MM when the duration is an hour or longer
+///
+/// The colon blinks while `running` is set
+fn render_digits(duration: Duration, color: Rgb888, running: bool) -> RgbImage {
+    let mut result = ImageBuffer::new();
+
+    let secs = duration.as_secs();
+    let (major, minor) = if secs >= 3600 {
+        (secs / 3600 % 100, secs / 60 % 60)
+    } else {
+        (secs / 60, secs % 60)
+    };
+
+    let colon = !running || duration.subsec_millis() < 500;
+
+    Text::with_text_style(
+        &format!("{major:0>2}{}{minor:0>2}", if colon { ":" } else { " " }),
+        TEXT_POSITION,
+        MonoTextStyle::new(&SEVENT_SEGMENT_FONT, color),
+        TEXT_STYLE,
+    )
+    .draw(&mut result)
+    .ok();
+
+    result.into_image()
+}
@@ -0,0 +1,297 @@
+use std::time::Duration;
+
+use image::{Rgb, RgbImage};
+
+/// Curve used to get from one keyframe to the next
+#[derive(Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+
+    InQuad,
+    OutQuad,
+    InOutQuad,
+
+    InCubic,
+    OutCubic,
+    InOutCubic,
+
+    /// Overshoots the target a little, then settles
+    OutBack,
+    OutBounce,
+
+    /// Stays at the previous value, and jumps once the keyframe is reached
+    Hold,
+}
+
+impl Easing {
+    /// Maps progress `t` (0.0 to 1.0) between two keyframes onto the curve
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Self::Linear => t,
+
+            Self::InQuad => t * t,
+            Self::OutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Self::InOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+
+            Self::InCubic => t * t * t,
+            Self::OutCubic => 1.0 - (1.0 - t).powi(3),
+            Self::InOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+
+            Self::OutBack => {
+                const C1: f32 = 1.70158;
+                const C3: f32 = C1 + 1.0;
+
+                1.0 + C3 * (t - 1.0).powi(3) + C1 * (t - 1.0).powi(2)
+            }
+            Self::OutBounce => {
+                const N1: f32 = 7.5625;
+                const D1: f32 = 2.75;
+
+                if t < 1.0 / D1 {
+                    N1 * t * t
+                } else if t < 2.0 / D1 {
+                    let t = t - 1.5 / D1;
+                    N1 * t * t + 0.75
+                } else if t < 2.5 / D1 {
+                    let t = t - 2.25 / D1;
+                    N1 * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / D1;
+                    N1 * t * t + 0.984375
+                }
+            }
+
+            Self::Hold => {
+                if t < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+
+/// Values that can be interpolated between keyframes
+pub trait Tween: Copy {
+    fn tween(self, to: Self, t: f32) -> Self;
+}
+
+impl Tween for f32 {
+    fn tween(self, to: Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Tween for (f32, f32) {
+    fn tween(self, to: Self, t: f32) -> Self {
+        (self.0.tween(to.0, t), self.1.tween(to.1, t))
+    }
+}
+
+impl Tween for Rgb<u8> {
+    fn tween(self, to: Self, t: f32) -> Self {
+        let channel = |i: usize| {
+            (self.0[i] as f32)
+                .tween(to.0[i] as f32, t)
+                .round()
+                .clamp(0.0, 255.0) as u8
+        };
+
+        Rgb([channel(0), channel(1), channel(2)])
+    }
+}
+
+struct Keyframe<T> {
+    at: Duration,
+    value: T,
+    easing: Easing,
+}
+
+/// A value that changes over time by moving between keyframes
+///
+/// Before the first keyframe the track holds its initial value, after the last one it holds
+/// the value of that keyframe.
+pub struct Track<T: Tween> {
+    initial: T,
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Tween> Track<T> {
+    /// A track that stays at `value` until keyframes are added
+    pub fn new(value: T) -> Self {
+        Self {
+            initial: value,
+            keyframes: vec![],
+        }
+    }
+
+    /// Adds a keyframe that reaches `value` at `at`, following `easing` from the previous one
+    ///
+    /// Keyframes have to be added in order.
+    pub fn to(mut self, at: Duration, value: T, easing: Easing) -> Self {
+        debug_assert!(self.duration() <= at, "Keyframes have to be added in order");
+
+        self.keyframes.push(Keyframe { at, value, easing });
+        self
+    }
+
+    /// Time of the last keyframe
+    pub fn duration(&self) -> Duration {
+        self.keyframes
+            .last()
+            .map(|keyframe| keyframe.at)
+            .unwrap_or(Duration::ZERO)
+    }
+
+    pub fn sample(&self, t: Duration) -> T {
+        let mut from = (Duration::ZERO, self.initial);
+
+        for keyframe in &self.keyframes {
+            if t < keyframe.at {
+                let span = (keyframe.at - from.0).as_secs_f32();
+                let progress = (t - from.0).as_secs_f32() / span;
+
+                return from
+                    .1
+                    .tween(keyframe.value, keyframe.easing.apply(progress));
+            }
+
+            from = (keyframe.at, keyframe.value);
+        }
+
+        from.1
+    }
+}
+
+/// An image with tracks for where and how it's drawn
+pub struct Sprite {
+    image: RgbImage,
+
+    /// Top left corner
+    position: Track<(f32, f32)>,
+
+    /// Scales from the top left corner
+    scale: Track<f32>,
+    opacity: Track<f32>,
+
+    /// Multiplied with the colors of the image, white keeps the original colors
+    tint: Track<Rgb<u8>>,
+
+    /// Part of the image that is visible, from left to right
+    reveal: Track<f32>,
+}
+
+impl Sprite {
+    pub fn new(image: RgbImage) -> Self {
+        Self {
+            image,
+
+            position: Track::new((0.0, 0.0)),
+            scale: Track::new(1.0),
+            opacity: Track::new(1.0),
+            tint: Track::new(Rgb([255, 255, 255])),
+            reveal: Track::new(1.0),
+        }
+    }
+
+    pub fn position(mut self, track: Track<(f32, f32)>) -> Self {
+        self.position = track;
+        self
+    }
+
+    pub fn scale(mut self, track: Track<f32>) -> Self {
+        self.scale = track;
+        self
+    }
+
+    pub fn opacity(mut self, track: Track<f32>) -> Self {
+        self.opacity = track;
+        self
+    }
+
+    pub fn tint(mut self, track: Track<Rgb<u8>>) -> Self {
+        self.tint = track;
+        self
+    }
+
+    pub fn reveal(mut self, track: Track<f32>) -> Self {
+        self.reveal = track;
+        self
+    }
+
+    /// Time after which none of the tracks change anymore
+    pub fn duration(&self) -> Duration {
+        [
+            self.position.duration(),
+            self.scale.duration(),
+            self.opacity.duration(),
+            self.tint.duration(),
+            self.reveal.duration(),
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or(Duration::ZERO)
+    }
+
+    /// Draws the sprite as it is at `t` on top of `target`
+    pub fn draw(&self, target: &mut RgbImage, t: Duration) {
+        let (x, y) = self.position.sample(t);
+        let scale = self.scale.sample(t).max(0.0);
+        let opacity = self.opacity.sample(t).clamp(0.0, 1.0);
+        let tint = self.tint.sample(t);
+        let reveal = self.reveal.sample(t).clamp(0.0, 1.0);
+
+        if scale == 0.0 || opacity == 0.0 {
+            return;
+        }
+
+        let visible = (self.image.width() as f32 * reveal) as u32;
+        let width = (self.image.width() as f32 * scale).ceil() as i64;
+        let height = (self.image.height() as f32 * scale).ceil() as i64;
+        let (x, y) = (x.round() as i64, y.round() as i64);
+
+        for dy in 0..height {
+            for dx in 0..width {
+                let (tx, ty) = (x + dx, y + dy);
+
+                if !(0..target.width() as i64).contains(&tx)
+                    || !(0..target.height() as i64).contains(&ty)
+                {
+                    continue;
+                }
+
+                let sx = (dx as f32 / scale) as u32;
+                let sy = (dy as f32 / scale) as u32;
+
+                if sx >= visible || sy >= self.image.height() {
+                    continue;
+                }
+
+                let source = self.image.get_pixel(sx, sy);
+                let pixel = target.get_pixel_mut(tx as u32, ty as u32);
+
+                for i in 0..3 {
+                    let value = source.0[i] as f32 * tint.0[i] as f32 / 255.0;
+                    let blended = pixel.0[i] as f32 * (1.0 - opacity) + value * opacity;
+
+                    pixel.0[i] = blended.round() as u8;
+                }
+            }
+        }
+    }
+}
@@ -2,16 +2,119 @@ use embedded_graphics::{
     pixelcolor::{raw::ToBytes, Rgb888},
     prelude::RgbColor,
 };
-use rand::Rng;
+use image::Rgb;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::image::hsl_to_rgb;
+
+const HALLOWEEN: &[Rgb888] = &[
+    Rgb888::new(255, 117, 24),
+    Rgb888::new(140, 30, 200),
+    Rgb888::new(90, 220, 40),
+    Rgb888::new(255, 60, 0),
+];
+
+const NEON: &[Rgb888] = &[
+    Rgb888::new(255, 0, 200),
+    Rgb888::new(0, 255, 240),
+    Rgb888::new(180, 255, 0),
+    Rgb888::new(140, 0, 255),
+];
+
+const PASTEL: &[Rgb888] = &[
+    Rgb888::new(255, 179, 186),
+    Rgb888::new(255, 223, 186),
+    Rgb888::new(255, 255, 186),
+    Rgb888::new(186, 255, 201),
+    Rgb888::new(186, 225, 255),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Palette {
+    /// 2-5 saturated colors with random hues, the same seed always gives the same colors
+    Random(u64),
+
+    Halloween,
+    Neon,
+    Pastel,
+}
 
-pub fn generate_table() -> Vec<Rgb888> {
-    let mut rng = rand::thread_rng();
-    let amount = rng.gen_range(2..6);
+impl Palette {
+    /// A random palette with a fresh seed
+    pub fn random() -> Self {
+        Self::Random(rand::random())
+    }
 
-    let colors = vec![0; amount]
-        .into_iter()
-        .map(|_| Rgb888::new(rand::random(), rand::random(), rand::random()))
-        .collect::<Vec<_>>();
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Random(_) => "Random",
+            Self::Halloween => "Halloween",
+            Self::Neon => "Neon",
+            Self::Pastel => "Pastel",
+        }
+    }
+
+    /// The palette that comes after this one when cycling through them on the deck
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Random(_) => Self::Halloween,
+            Self::Halloween => Self::Neon,
+            Self::Neon => Self::Pastel,
+            Self::Pastel => Self::random(),
+        }
+    }
+
+    /// Picks a new seed for random palettes, named palettes are left alone
+    pub fn reseed<R: Rng>(&mut self, rng: &mut R) {
+        if let Self::Random(_) = self {
+            *self = Self::Random(rng.gen());
+        }
+    }
+
+    pub fn colors(&self) -> Vec<Rgb888> {
+        match self {
+            Self::Random(seed) => {
+                let mut rng = StdRng::seed_from_u64(*seed);
+                let amount = rng.gen_range(2..6);
+
+                (0..amount)
+                    .map(|_| {
+                        let rgb = hsl_to_rgb(rng.gen_range(0..360), 1.0, 0.5);
+                        Rgb888::new(rgb.0[0], rgb.0[1], rgb.0[2])
+                    })
+                    .collect()
+            }
+            Self::Halloween => HALLOWEEN.to_vec(),
+            Self::Neon => NEON.to_vec(),
+            Self::Pastel => PASTEL.to_vec(),
+        }
+    }
+
+    /// Picks a random color that fits the palette
+    ///
+    /// Random palettes pick from the entire hue range, instead of only their own few colors
+    pub fn pick<R: Rng>(&self, rng: &mut R) -> Rgb<u8> {
+        match self {
+            Self::Random(_) => hsl_to_rgb(rng.gen_range(0..360), 1.0, 0.5),
+            _ => {
+                let colors = self.colors();
+                let color = colors[rng.gen_range(0..colors.len())];
+
+                Rgb([color.r(), color.g(), color.b()])
+            }
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::random()
+    }
+}
+
+/// Generates a looping gradient through the colors of `palette`
+pub fn generate_table(palette: &Palette) -> Vec<Rgb888> {
+    let colors = palette.colors();
 
     let gradient_len = 95;
     let mut image = vec![0; 95 * 3];
@@ -56,7 +159,8 @@ fn reverse_rgb_data(data: &mut Vec<u8>, bytes_per_pixel: usize) {
     }
 }
 
-fn interpolate_colors(colors: &[Rgb888], t: f32) -> Rgb888 {
+/// Color at `t` (0.0 to 1.0) along a gradient through `colors`
+pub fn interpolate_colors(colors: &[Rgb888], t: f32) -> Rgb888 {
     let segment = t * (colors.len() - 1) as f32;
     let index = segment.floor() as usize;
     let t_segment = segment - index as f32;
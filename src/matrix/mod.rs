@@ -7,6 +7,12 @@ use std::{
     sync::mpsc::{Receiver, SyncSender, TryRecvError},
 };
 
+#[cfg(test)]
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
 use anyhow::{anyhow, Result};
 use embedded_graphics::{
     pixelcolor::{raw::ToBytes, Rgb888},
@@ -15,7 +21,7 @@ use embedded_graphics::{
 use image::RgbImage;
 use rpi_led_panel::{Canvas, HardwareMapping, RGBMatrix, RGBMatrixConfig};
 
-use animations::Animation;
+use animations::{Animation, AnimationContext};
 
 #[cfg(debug_assertions)]
 const SLOWDOWN: u32 = 2;
@@ -43,6 +49,21 @@ enum SchedulerCommand {
     SetBrightness(u8),
 }
 
+/// What a headless [`Matrix`] would show, see [`Matrix::headless_with`]
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct HeadlessPanel {
+    animating: Arc<AtomicBool>,
+}
+
+#[cfg(test)]
+impl HeadlessPanel {
+    /// Whether the panel plays an animation, instead of showing an image or a color
+    pub fn is_animating(&self) -> bool {
+        self.animating.load(Ordering::Relaxed)
+    }
+}
+
 pub struct Matrix {
     tx: SyncSender<SchedulerCommand>,
     rgb_buffer: [u8; MATRIX_FULL_IMAGE_LENGTH],
@@ -92,6 +113,35 @@ impl Matrix {
         })
     }
 
+    /// A matrix without a panel behind it, for tests of the menus
+    ///
+    /// Every command is accepted, and dropped right away.
+    #[cfg(test)]
+    pub fn headless() -> Self {
+        Self::headless_with(HeadlessPanel::default())
+    }
+
+    /// A matrix without a panel behind it, that tells `panel` what it would show
+    #[cfg(test)]
+    pub fn headless_with(panel: HeadlessPanel) -> Self {
+        let (tx, rx) = std::sync::mpsc::sync_channel(1);
+
+        std::thread::spawn(move || {
+            for command in rx {
+                if let SchedulerCommand::UpdateState(state) = command {
+                    let animating = matches!(state, State::Animation(_));
+
+                    panel.animating.store(animating, Ordering::Relaxed);
+                }
+            }
+        });
+
+        Self {
+            tx,
+            rgb_buffer: [0; MATRIX_FULL_IMAGE_LENGTH],
+        }
+    }
+
     /// Takes the current [`Self::rgb_buffer`] and writes it as an image to the scheduler
     pub fn flush_image(&self) -> Result<()> {
         let image = RgbImage::from_raw(64, 32, self.rgb_buffer.to_vec()).expect("huh");
@@ -171,6 +221,7 @@ impl DrawTarget for Matrix {
 fn scheduler(mut matrix: RGBMatrix, mut canvas: Box<Canvas>, rx: Receiver<SchedulerCommand>) {
     let mut brightness = DEFAULT_BRIGHTNESS;
     let mut state: State = State::Noop;
+    let mut ctx = AnimationContext::new();
 
     loop {
         match rx.try_recv() {
@@ -183,12 +234,12 @@ fn scheduler(mut matrix: RGBMatrix, mut canvas: Box<Canvas>, rx: Receiver<Schedu
         }
 
         canvas.set_brightness(brightness);
-        update_state_canvas(&mut canvas, &mut state);
+        update_state_canvas(&mut canvas, &mut state, &mut ctx);
         matrix.update_on_vsync(canvas.clone());
     }
 }
 
-fn update_state_canvas(canvas: &mut Box<Canvas>, state: &mut State) {
+fn update_state_canvas(canvas: &mut Box<Canvas>, state: &mut State, ctx: &mut AnimationContext) {
     match state {
         State::Noop => {}
         State::Solid(r, g, b) => {
@@ -209,11 +260,11 @@ fn update_state_canvas(canvas: &mut Box<Canvas>, state: &mut State) {
             }
         }
         State::Animation(animation) => {
-            if !animation.should_execute() {
+            if !animation.should_execute(ctx) {
                 return;
             }
 
-            let Some(image) = animation.next_frame() else {
+            let Some(image) = animation.next_frame(ctx) else {
                 // When animation has finished and looping is disabled
                 *state = State::Noop;
                 return;
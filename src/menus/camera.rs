@@ -1,13 +1,10 @@
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
-use streamdeck_hid_rs::ButtonState;
 
 use crate::{image::ImageSourceType, matrix::animations::SmileAnimation, AppState};
 
-const IMG_BACK: ImageSourceType = ImageSourceType::Jpeg(include_bytes!("../../images/back.jpg"));
-const IMG_BACK_DOWN: ImageSourceType =
-    ImageSourceType::Jpeg(include_bytes!("../../images/back_down.jpg"));
+use super::page::{self, Button, Page};
 
 const IMG_CAMERA: ImageSourceType =
     ImageSourceType::Jpeg(include_bytes!("../../images/camera.jpg"));
@@ -50,7 +47,7 @@ impl FlashState {
         }
     }
 
-    pub fn image(&self, down: bool) -> ImageSourceType<'_> {
+    pub fn image(&self, down: bool) -> ImageSourceType<'static> {
         match (self, down) {
             (Self::Off, true) => IMG_FLASH_OFF_DOWN,
             (Self::Off, false) => IMG_FLASH_OFF,
@@ -77,99 +74,70 @@ impl FlashState {
 }
 
 pub fn launch(state: &mut AppState) -> Result<()> {
-    let mut flash = FlashState::FlashAndPreview;
-    let mut timer = false;
-
-    state.deck.clear()?;
-
-    state.deck.set_button_image(0, IMG_BACK)?;
-    state.deck.set_button_image(2, IMG_TIMER_OFF)?;
-    state.deck.set_button_image(3, flash.image(false))?;
-    state.deck.set_button_image(4, IMG_CAMERA)?;
-
-    loop {
-        let message = state.deck.next_btn_event()?;
-
-        match message.button_id {
-            0 => {
-                if matches!(message.state, ButtonState::Up) {
-                    break;
-                } else {
-                    state
-                        .deck
-                        .set_button_image(message.button_id as u8, IMG_BACK_DOWN)?;
-                }
-            }
-
-            2 => {
-                if matches!(message.state, ButtonState::Up) {
-                    timer = !timer;
-
-                    state.deck.set_button_image(
-                        message.button_id as u8,
-                        if timer { IMG_TIMER } else { IMG_TIMER_OFF },
-                    )?;
-                } else {
-                    state.deck.set_button_image(
-                        message.button_id as u8,
-                        if timer {
-                            IMG_TIMER_DOWN
-                        } else {
-                            IMG_TIMER_OFF_DOWN
-                        },
-                    )?;
-                }
-            }
-
-            3 => {
-                if matches!(message.state, ButtonState::Up) {
-                    flash.advance();
-
-                    state
-                        .deck
-                        .set_button_image(message.button_id as u8, flash.image(false))?;
-                } else {
-                    state
-                        .deck
-                        .set_button_image(message.button_id as u8, flash.image(true))?;
-                }
-            }
-
-            4 => {
-                if matches!(message.state, ButtonState::Up) {
+    let mut page = CameraPage {
+        flash: FlashState::FlashAndPreview,
+        timer: false,
+    };
+
+    page::run(state, &mut page)
+}
+
+struct CameraPage {
+    flash: FlashState,
+    timer: bool,
+}
+
+impl Page for CameraPage {
+    fn buttons(&self, _state: &AppState) -> Result<Vec<Button<Self>>> {
+        let (timer, timer_down) = if self.timer {
+            (IMG_TIMER, IMG_TIMER_DOWN)
+        } else {
+            (IMG_TIMER_OFF, IMG_TIMER_OFF_DOWN)
+        };
+
+        Ok(vec![
+            Button::back(),
+            Button::new(2, timer)
+                .pressed(timer_down)
+                .run(|page: &mut Self, _| {
+                    page.timer = !page.timer;
+
+                    Ok(())
+                }),
+            Button::new(3, self.flash.image(false))
+                .pressed(self.flash.image(true))
+                .run(|page: &mut Self, _| {
+                    page.flash.advance();
+
+                    Ok(())
+                }),
+            Button::new(4, IMG_CAMERA)
+                .pressed(IMG_CAMERA_DOWN)
+                .run(|page: &mut Self, state| {
+                    let (flash, preview) = (page.flash.is_flash(), page.flash.is_preview());
+
                     state.deck.clear()?;
 
                     // Capture image, store on disk and display on StreamDeck
-                    capture_image(state, flash.is_flash(), flash.is_preview(), timer)?;
+                    capture_image(state, flash, preview, page.timer)?;
                     state.deck.flush_btn_events()?;
 
-                    // Press any button to clear image and go back to camera interface
-                    state.deck.wait_for_any_press()?;
-                    state.deck.clear()?;
+                    // Press any button to clear image and go back to camera interface, the matrix
+                    // is put back also when back is held to go home
+                    let pressed = state.deck.wait_for_any_press();
 
-                    if flash.is_preview() || timer {
+                    if preview || page.timer {
                         state.matrix.clear()?;
                         state.start_matrix_animation();
                     }
 
-                    state.deck.set_button_image(0, IMG_BACK)?;
-                    state
-                        .deck
-                        .set_button_image(2, if timer { IMG_TIMER } else { IMG_TIMER_OFF })?;
-                    state.deck.set_button_image(3, flash.image(false))?;
-                    state.deck.set_button_image(4, IMG_CAMERA)?;
-                } else {
-                    state
-                        .deck
-                        .set_button_image(message.button_id as u8, IMG_CAMERA_DOWN)?;
-                }
-            }
-
-            _ => {}
-        }
-    }
+                    pressed?;
+                    state.deck.clear()?;
 
-    Ok(())
+                    Ok(())
+                }),
+        ])
+    }
 }
 
 fn capture_image(state: &mut AppState, flash: bool, preview: bool, timer: bool) -> Result<()> {
@@ -186,23 +154,25 @@ fn capture_image(state: &mut AppState, flash: bool, preview: bool, timer: bool)
         state.start_matrix_animation();
     }
 
-    let start = SystemTime::now();
-    let unix = start.duration_since(UNIX_EPOCH)?.as_secs();
+    if state.camera.keeps_photos() {
+        let start = SystemTime::now();
+        let unix = start.duration_since(UNIX_EPOCH)?.as_secs();
 
-    let filename = format!("captures/{unix}.jpg");
-    let jpeg = crate::image::encode_jpeg(&image, image.width(), image.height())?;
-    std::fs::write(&filename, jpeg)?;
+        let filename = format!("captures/{unix}.jpg");
+        let jpeg = crate::image::encode_jpeg(&image, image.width(), image.height())?;
+        std::fs::write(&filename, jpeg)?;
 
-    std::thread::spawn({
-        let filename = filename.clone();
+        std::thread::spawn({
+            let filename = filename.clone();
 
-        move || crate::cloud::upload_file(&filename).ok()
-    });
+            move || crate::cloud::upload_file(&filename).ok()
+        });
+    }
 
-    let resized = crate::image::resize(&image, 384, 216, true);
+    // Cropped to the shape of the deck, not squashed into it
     state
         .deck
-        .set_fullscreen_image(ImageSourceType::Rgb(resized))?;
+        .set_fullscreen_image(ImageSourceType::Rgb(image.clone()))?;
 
     if preview {
         let resized = crate::image::resize(&image, 64, 32, true);
@@ -0,0 +1,214 @@
+//! Menus that are read from [`MENUS_FILE`], see the `menus.toml` in the repository for the
+//! format
+//!
+//! The file is read every time a menu that uses it is opened, so it can be changed while the app
+//! is running.
+
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+use crate::{render::Background, state::MatrixAnimation};
+
+pub const MENUS_FILE: &str = "menus.toml";
+
+const DEFAULT_MENUS: &str = include_str!("../../menus.toml");
+
+// Custom pages are on the second row of the main menu
+pub const MAX_PAGES: usize = 5;
+
+/// Keys of the matrix menu that its built-in buttons leave free for animations
+pub const MATRIX_KEYS: [u8; 3] = [10, 11, 12];
+
+/// The menus, what a menus file leaves out is taken from the built-in menus
+#[derive(Deserialize)]
+pub struct MenuConfig {
+    /// Emojis of the emoji menu, by their name in the emoji pack
+    #[serde(default = "builtin_emojis")]
+    pub emojis: Vec<String>,
+
+    /// Emojis of the double emoji menu
+    #[serde(default = "builtin_double_emojis")]
+    pub double_emojis: Vec<String>,
+
+    /// Animations of the matrix menu, on the [`MATRIX_KEYS`]
+    #[serde(default = "builtin_matrix")]
+    pub matrix: Vec<ButtonConfig>,
+
+    #[serde(default)]
+    pub pages: Vec<PageConfig>,
+}
+
+fn builtin() -> MenuConfig {
+    toml::from_str(DEFAULT_MENUS).expect("built-in menus are valid")
+}
+
+fn builtin_emojis() -> Vec<String> {
+    builtin().emojis
+}
+
+fn builtin_double_emojis() -> Vec<String> {
+    builtin().double_emojis
+}
+
+fn builtin_matrix() -> Vec<ButtonConfig> {
+    builtin().matrix
+}
+
+/// A page of buttons from the config, that is opened from the main menu
+#[derive(Clone, Deserialize)]
+pub struct PageConfig {
+    /// Text on the main menu key that opens the page
+    pub label: String,
+
+    #[serde(default)]
+    pub buttons: Vec<ButtonConfig>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ButtonConfig {
+    pub key: u8,
+    pub label: Option<String>,
+
+    /// Image on the key, above the label when there is one
+    pub icon: Option<PathBuf>,
+
+    /// Image on the key while it is held down
+    pub pressed_icon: Option<PathBuf>,
+
+    /// Color or gradient behind the label, the emoji and the icon, black without it
+    pub background: Option<Background>,
+
+    #[serde(flatten)]
+    pub action: ButtonAction,
+}
+
+impl ButtonConfig {
+    /// Whether the button shows something else than its background
+    pub fn has_face(&self) -> bool {
+        self.label.is_some() || self.icon.is_some() || matches!(self.action, ButtonAction::Emoji(_))
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ButtonAction {
+    /// Starts an animation on the matrix
+    Animation(MatrixAnimation),
+
+    /// Shows an emoji from the emoji pack on the matrix
+    Emoji(String),
+}
+
+impl MenuConfig {
+    /// Reads [`MENUS_FILE`], or the built-in menus when there is none
+    ///
+    /// A file with mistakes in it is reported, and the built-in menus are used instead.
+    pub fn load() -> Self {
+        match fs::read_to_string(MENUS_FILE) {
+            Ok(menus) => match Self::parse(&menus) {
+                Ok(config) => return config,
+                Err(why) => log::warn!("Failed to load {MENUS_FILE}: {why:#}"),
+            },
+            Err(why) if why.kind() == ErrorKind::NotFound => {}
+            Err(why) => log::warn!("Failed to read {MENUS_FILE}: {why}"),
+        }
+
+        Self::parse(DEFAULT_MENUS).expect("built-in menus are valid")
+    }
+
+    pub fn parse(menus: &str) -> Result<Self> {
+        let config: Self = toml::from_str(menus)?;
+
+        if config.pages.len() > MAX_PAGES {
+            return Err(anyhow!("There is only room for {MAX_PAGES} pages"));
+        }
+
+        if let Some(button) = config
+            .matrix
+            .iter()
+            .find(|button| !MATRIX_KEYS.contains(&button.key))
+        {
+            return Err(anyhow!(
+                "Key {} of the matrix menu is taken, keys {MATRIX_KEYS:?} are free",
+                button.key
+            ));
+        }
+
+        check_twice(&config.matrix, "the matrix menu")?;
+
+        for page in &config.pages {
+            if let Some(button) = page
+                .buttons
+                .iter()
+                .find(|button| !(1..15).contains(&button.key))
+            {
+                return Err(anyhow!(
+                    "Key {} on page {} does not exist, keys go from 1 to 14",
+                    button.key,
+                    page.label
+                ));
+            }
+
+            check_twice(&page.buttons, &format!("page {}", page.label))?;
+        }
+
+        Ok(config)
+    }
+}
+
+fn check_twice(buttons: &[ButtonConfig], menu: &str) -> Result<()> {
+    for (idx, button) in buttons.iter().enumerate() {
+        if buttons[..idx].iter().any(|other| other.key == button.key) {
+            return Err(anyhow!("Key {} is on {menu} twice", button.key));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn menus_file_mistakes() {
+        let menus = |pages: &str| format!("emojis = []\ndouble_emojis = []\n{pages}");
+
+        assert!(MenuConfig::parse(&menus("")).is_ok());
+
+        // What is left out comes from the built-in menus
+        let empty = MenuConfig::parse("").unwrap();
+        assert!(empty.emojis.contains(&"ghost".to_string()));
+        assert_eq!(empty.matrix.len(), 3);
+
+        let taken_key = r#"
+            [[matrix]]
+            key = 13
+            animation = "sequence"
+        "#;
+        assert!(MenuConfig::parse(&menus(taken_key)).is_err());
+
+        let wrong_key = r#"
+            [[pages]]
+            label = "Party"
+            buttons = [{ key = 15, animation = "sequence" }]
+        "#;
+        assert!(MenuConfig::parse(&menus(wrong_key)).is_err());
+
+        let twice = r#"
+            [[pages]]
+            label = "Party"
+            buttons = [{ key = 1, emoji = "ghost" }, { key = 1, animation = "sequence" }]
+        "#;
+        assert!(MenuConfig::parse(&menus(twice)).is_err());
+
+        let unknown_animation = r#"
+            [[pages]]
+            label = "Party"
+            buttons = [{ key = 1, animation = "disco" }]
+        "#;
+        assert!(MenuConfig::parse(&menus(unknown_animation)).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::{
+    image::{ImageSourceType, RgbImage},
+    render::KeyFace,
+    state::AppState,
+};
+
+use super::{
+    config::{ButtonAction, ButtonConfig, PageConfig},
+    matrix::emoji,
+    page::{self, Button, Page},
+};
+
+/// Runs a page from `menus.toml`
+pub fn launch(state: &mut AppState, config: PageConfig) -> Result<()> {
+    let buttons = config
+        .buttons
+        .into_iter()
+        .map(|config| ConfigButton::load(state, config))
+        .collect::<Result<_>>()?;
+
+    page::run(state, &mut CustomPage { buttons })
+}
+
+struct CustomPage {
+    buttons: Vec<ConfigButton>,
+}
+
+impl Page for CustomPage {
+    fn buttons(&self, _state: &AppState) -> Result<Vec<Button<Self>>> {
+        let mut buttons = vec![Button::back()];
+        buttons.extend(self.buttons.iter().map(ConfigButton::button));
+
+        Ok(buttons)
+    }
+}
+
+/// A button from `menus.toml`, with its face drawn and its images read when the page opens
+pub struct ConfigButton {
+    key: u8,
+    icon: ImageSourceType<'static>,
+    pressed: Option<ImageSourceType<'static>>,
+    action: ButtonAction,
+}
+
+impl ConfigButton {
+    pub fn load(state: &AppState, config: ButtonConfig) -> Result<Self> {
+        let pressed = config.pressed_icon.as_deref().and_then(open_icon);
+
+        Ok(Self {
+            key: config.key,
+            icon: icon(state, &config)?,
+            pressed: pressed.map(ImageSourceType::Rgb),
+            action: config.action,
+        })
+    }
+
+    /// A button from `config` with a built-in face, the face of the config is not drawn
+    pub fn with_icon(
+        config: ButtonConfig,
+        icon: ImageSourceType<'static>,
+        pressed: ImageSourceType<'static>,
+    ) -> Self {
+        Self {
+            key: config.key,
+            icon,
+            pressed: Some(pressed),
+            action: config.action,
+        }
+    }
+
+    pub fn button<P>(&self) -> Button<P> {
+        let mut button = Button::new(self.key, self.icon.clone());
+
+        if let Some(pressed) = &self.pressed {
+            button = button.pressed(pressed.clone());
+        }
+
+        let action = self.action.clone();
+
+        button.run(move |_, state| match &action {
+            ButtonAction::Animation(animation) => {
+                state.matrix_animation = animation.clone();
+                state.start_matrix_animation();
+
+                Ok(())
+            }
+            ButtonAction::Emoji(name) => emoji::show_emoji(state, name),
+        })
+    }
+}
+
+/// The background of the button with the emoji it shows, its image and its label on top
+///
+/// A button with none of them shows a question mark.
+fn icon(state: &AppState, config: &ButtonConfig) -> Result<ImageSourceType<'static>> {
+    let mut face = KeyFace::default();
+    let mut empty = true;
+
+    if let Some(background) = config.background {
+        face = face.background(background);
+    }
+
+    if let ButtonAction::Emoji(name) = &config.action {
+        face = face.emoji(&state.emojis, name);
+        empty = false;
+    }
+
+    if let Some(icon) = config.icon.as_deref().and_then(open_icon) {
+        face = face.icon(icon);
+        empty = false;
+    }
+
+    match &config.label {
+        Some(label) => face = face.label(label),
+        None if empty => face = face.label("?"),
+        None => {}
+    }
+
+    Ok(ImageSourceType::Rgb(face.render()?))
+}
+
+/// Reads an image for a key, images that can't be read are reported and left out
+fn open_icon(path: &Path) -> Option<RgbImage> {
+    match image::open(path) {
+        Ok(image) => Some(image.into_rgb8()),
+        Err(why) => {
+            log::warn!("Failed to load icon {}: {why}", path.display());
+            None
+        }
+    }
+}
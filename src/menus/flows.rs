@@ -0,0 +1,488 @@
+//! Menu flows, run on a [`VirtualDeck`] with a headless matrix and a still camera
+//!
+//! Every test runs a menu on its own thread and presses buttons like a person would: it waits
+//! for a page to show up before pressing the next button.
+
+use std::{
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use image::Rgb;
+use streamdeck_hid_rs::ButtonState;
+
+use crate::{
+    audio::AudioBuffer,
+    camera::CameraSource,
+    deck::{DeckLayout, DeckReceiver, VirtualDeck},
+    emoji::EmojiPack,
+    image::{self, ImageSourceType, RgbImage},
+    matrix::{
+        animations::{ClockFace, EyesControl, SpectrumMode},
+        color_utils::Palette,
+        HeadlessPanel, Matrix,
+    },
+    render::{parse_color, render_text, Background, KeyFace},
+    state::{AppState, Effect, MatrixAnimation},
+};
+
+// Long enough for a slow CI machine to decode and draw a page
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// How long a press that the menu might drop gets to show an effect, before it is repeated
+const RETRY: Duration = Duration::from_millis(100);
+
+const IMG_BACK: ImageSourceType = ImageSourceType::Jpeg(include_bytes!("../../images/back.jpg"));
+const IMG_BACK_DOWN: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/back_down.jpg"));
+const IMG_BLOCKS: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/blocks.jpg"));
+const IMG_CAMERA: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/camera.jpg"));
+const IMG_CLOCK: ImageSourceType = ImageSourceType::Jpeg(include_bytes!("../../images/clock.jpg"));
+const IMG_DOUBLE_EMOJI: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/double_emoji.jpg"));
+const IMG_EMOJI: ImageSourceType = ImageSourceType::Jpeg(include_bytes!("../../images/emoji.jpg"));
+const IMG_EYES: ImageSourceType = ImageSourceType::Jpeg(include_bytes!("../../images/eyes.jpg"));
+const IMG_FALLING: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/falling.jpg"));
+const IMG_FLASH_ON: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/flash_on.jpg"));
+const IMG_GAME: ImageSourceType = ImageSourceType::Jpeg(include_bytes!("../../images/game.jpg"));
+const IMG_MATRIX: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/matrix.jpg"));
+const IMG_MORE: ImageSourceType = ImageSourceType::Jpeg(include_bytes!("../../images/next.jpg"));
+const IMG_MORE_DOWN: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/next_down.jpg"));
+const IMG_SEQUENCE: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/sequence.jpg"));
+const IMG_TICTACTOE: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/tictactoe.jpg"));
+const IMG_TIMER: ImageSourceType = ImageSourceType::Jpeg(include_bytes!("../../images/timer.jpg"));
+const IMG_TIMER_DOWN: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/timer_down.jpg"));
+
+type Menu = fn(&mut AppState) -> Result<()>;
+
+/// Starts `menu` on a new thread, with a fresh [`AppState`] on a 15 key [`VirtualDeck`]
+fn launch(menu: Menu) -> (VirtualDeck, JoinHandle<(AppState, Result<()>)>) {
+    launch_on(DeckLayout::new(15, (72, 72)), menu)
+}
+
+fn launch_on(layout: DeckLayout, menu: Menu) -> (VirtualDeck, JoinHandle<(AppState, Result<()>)>) {
+    launch_with(VirtualDeck::with_layout(layout), menu)
+}
+
+fn launch_with(
+    deck: (DeckReceiver, VirtualDeck),
+    menu: Menu,
+) -> (VirtualDeck, JoinHandle<(AppState, Result<()>)>) {
+    launch_with_matrix(deck, Matrix::headless(), menu)
+}
+
+fn launch_with_matrix(
+    (deck, virtual_deck): (DeckReceiver, VirtualDeck),
+    matrix: Matrix,
+    menu: Menu,
+) -> (VirtualDeck, JoinHandle<(AppState, Result<()>)>) {
+    deck.start_event_loop();
+
+    let mut state = AppState {
+        deck,
+        matrix,
+        emojis: EmojiPack::default(),
+
+        matrix_animation: MatrixAnimation::Sequence,
+        palette: Palette::default(),
+        speed: 1.0,
+        audio: AudioBuffer::new(44100),
+        eyes: EyesControl::new(),
+        camera: CameraSource::Still(photo()),
+    };
+
+    let menu = std::thread::spawn(move || {
+        let result = menu(&mut state);
+
+        (state, result)
+    });
+
+    (virtual_deck, menu)
+}
+
+/// A gradient, so every key of the fullscreen preview looks different
+fn photo() -> RgbImage {
+    RgbImage::from_fn(640, 360, |x, y| {
+        Rgb([(x * 255 / 640) as u8, (y * 255 / 360) as u8, 128])
+    })
+}
+
+fn icon(source: ImageSourceType) -> RgbImage {
+    source.to_rgb(72, 72).unwrap()
+}
+
+fn text(label: &str) -> RgbImage {
+    render_text(label, 16).unwrap()
+}
+
+fn wait_for_key(deck: &VirtualDeck, index: u8, expected: &RgbImage) {
+    assert!(
+        deck.wait_for_key(index, expected, TIMEOUT),
+        "key {index} did not show the expected image"
+    );
+}
+
+/// Keeps pressing `button_id` until key `index` shows `expected`, for menus that drop the
+/// presses that come in while they are busy
+fn press_until(deck: &VirtualDeck, button_id: u8, index: u8, expected: &RgbImage) {
+    let start = Instant::now();
+
+    while start.elapsed() < TIMEOUT {
+        deck.press(button_id);
+
+        if deck.wait_for_key(index, expected, RETRY) {
+            return;
+        }
+    }
+
+    panic!("key {index} did not show the expected image after pressing {button_id}");
+}
+
+#[test]
+fn camera_capture_and_back() {
+    let (deck, menu) = launch(super::camera::launch);
+
+    wait_for_key(&deck, 4, &icon(IMG_CAMERA));
+
+    // Flash and preview, to flash only
+    deck.press(3);
+    wait_for_key(&deck, 3, &icon(IMG_FLASH_ON));
+
+    // The photo is shown over all keys
+    let preview = image::resize(&photo(), 384, 216, true);
+    let keys = DeckLayout::new(15, (72, 72)).split(&preview);
+
+    // Keys are drawn in order, so the rest is done when the last one is
+    deck.press(4);
+    wait_for_key(&deck, 14, &keys[14]);
+
+    for (index, key) in keys.iter().enumerate() {
+        assert!(
+            deck.key(index as u8) == *key,
+            "key {index} is not the photo"
+        );
+    }
+
+    // Any button goes back to the camera page
+    press_until(&deck, 7, 4, &icon(IMG_CAMERA));
+    assert!(deck.key(3) == icon(IMG_FLASH_ON), "flash setting was lost");
+
+    deck.press(0);
+
+    let (_, result) = menu.join().unwrap();
+    result.unwrap();
+
+    assert!(deck.key(0) == icon(IMG_BACK_DOWN));
+}
+
+#[test]
+fn music_from_main_menu() {
+    let (deck, menu) = launch(super::main::launch);
+
+    wait_for_key(&deck, 3, &text("Music"));
+
+    deck.press(3);
+    wait_for_key(&deck, 1, &text("Bars"));
+
+    deck.press(1);
+    wait_for_key(&deck, 1, &text("[Bars]"));
+
+    deck.press(0);
+    wait_for_key(&deck, 0, &icon(IMG_CAMERA));
+
+    // The main menu never returns by itself, only when the deck goes away
+    deck.unplug();
+
+    let (state, result) = menu.join().unwrap();
+
+    assert!(result.is_err());
+    assert!(matches!(
+        state.matrix_animation,
+        MatrixAnimation::Spectrum(SpectrumMode::Bars)
+    ));
+}
+
+#[test]
+fn pages_redraw_their_buttons() {
+    let (deck, menu) = launch(super::matrix::launch);
+
+    // The pressed icon is shown while the key is down, the sub-page opens when it comes up
+    wait_for_key(&deck, 2, &icon(IMG_TIMER));
+    deck.send(2, ButtonState::Down);
+    wait_for_key(&deck, 2, &icon(IMG_TIMER_DOWN));
+
+    deck.send(2, ButtonState::Up);
+    wait_for_key(&deck, 10, &render_text("0:00", 24).unwrap());
+
+    // The stopwatch has no presets, their keys are cleared
+    deck.press(1);
+    wait_for_key(&deck, 1, &render_text("Watch", 20).unwrap());
+    assert!(deck.key(10) == RgbImage::new(72, 72));
+
+    // The matrix page is drawn again when the timer is left
+    deck.press(0);
+    wait_for_key(&deck, 2, &icon(IMG_TIMER));
+
+    deck.press(0);
+
+    let (_, result) = menu.join().unwrap();
+    result.unwrap();
+}
+
+#[test]
+fn held_back_goes_home() {
+    let (deck, menu) = launch(super::main::launch);
+
+    wait_for_key(&deck, 1, &icon(IMG_MATRIX));
+
+    deck.press(1);
+    wait_for_key(&deck, 2, &icon(IMG_TIMER));
+
+    deck.press(2);
+    wait_for_key(&deck, 4, &render_text("Reset", 20).unwrap());
+
+    // Both pages are left while back is still held
+    deck.send(0, ButtonState::Down);
+    wait_for_key(&deck, 1, &icon(IMG_MATRIX));
+
+    // Letting go doesn't take the photo on the camera key that is now under the finger
+    deck.send(0, ButtonState::Up);
+    deck.press(3);
+    wait_for_key(&deck, 1, &text("Bars"));
+
+    deck.unplug();
+
+    let (_, result) = menu.join().unwrap();
+    assert!(result.is_err());
+}
+
+#[test]
+fn held_back_leaves_game() {
+    let panel = HeadlessPanel::default();
+    let (deck, menu) = launch_with_matrix(
+        VirtualDeck::open(),
+        Matrix::headless_with(panel.clone()),
+        super::main::launch,
+    );
+
+    wait_for_key(&deck, 2, &icon(IMG_GAME));
+
+    deck.press(2);
+    wait_for_key(&deck, 4, &icon(IMG_TICTACTOE));
+
+    deck.press(4);
+    wait_for_key(&deck, 5, &icon(IMG_BACK));
+
+    // The game is left while back is still held, and takes its board off the matrix
+    deck.send(0, ButtonState::Down);
+    wait_for_key(&deck, 0, &icon(IMG_CAMERA));
+    deck.send(0, ButtonState::Up);
+
+    deck.unplug();
+
+    let (state, result) = menu.join().unwrap();
+
+    assert!(result.is_err());
+    assert!(matches!(state.matrix_animation, MatrixAnimation::Sequence));
+    assert!(panel.is_animating(), "the board was left on the matrix");
+}
+
+#[test]
+fn custom_page_from_menus_file() {
+    let (deck, menu) = launch(super::main::launch);
+
+    wait_for_key(&deck, 5, &text("Party"));
+
+    deck.press(5);
+    wait_for_key(&deck, 1, &text("Plasma"));
+
+    let cheers = KeyFace::default()
+        .background(Background::Gradient(
+            parse_color("#c08000").unwrap(),
+            parse_color("#402000").unwrap(),
+        ))
+        .emoji(&EmojiPack::default(), "clinking-beer-mugs")
+        .label("Cheers")
+        .render()
+        .unwrap();
+    wait_for_key(&deck, 14, &cheers);
+
+    deck.press(1);
+    deck.press(0);
+    wait_for_key(&deck, 0, &icon(IMG_CAMERA));
+
+    deck.unplug();
+
+    let (state, result) = menu.join().unwrap();
+
+    assert!(result.is_err());
+    assert!(matches!(
+        state.matrix_animation,
+        MatrixAnimation::Effect(Effect::Plasma)
+    ));
+}
+
+#[test]
+fn main_menu_on_xl_deck() {
+    let layout = DeckLayout::new(32, (96, 96));
+    let (deck, menu) = launch_on(layout, super::main::launch);
+
+    let key = |image: RgbImage| image::resize(&image, 96, 96, true);
+
+    // The 5x3 menu sits in the middle of the 8x4 grid, the bottom row stays empty
+    wait_for_key(&deck, 1, &key(icon(IMG_CAMERA)));
+    wait_for_key(&deck, 4, &key(text("Music")));
+    assert!(deck.key(0) == RgbImage::new(96, 96));
+    assert!(deck.key(24) == RgbImage::new(96, 96));
+
+    deck.press(4);
+    wait_for_key(&deck, 2, &key(text("Bars")));
+
+    // Keys outside of the menu do nothing
+    deck.press(0);
+    deck.press(2);
+    wait_for_key(&deck, 2, &key(text("[Bars]")));
+
+    deck.unplug();
+
+    let (_, result) = menu.join().unwrap();
+    assert!(result.is_err());
+}
+
+#[test]
+fn main_menu_on_mini_deck() {
+    let (deck, menu) = launch_on(DeckLayout::new(6, (80, 80)), super::main::launch);
+
+    let key = |image: RgbImage| image::resize(&image, 80, 80, true);
+
+    // Not all buttons have a key on the menu grid, so they follow each other
+    wait_for_key(&deck, 0, &key(icon(IMG_CAMERA)));
+    wait_for_key(&deck, 1, &key(icon(IMG_MATRIX)));
+    wait_for_key(&deck, 3, &key(text("Music")));
+    wait_for_key(&deck, 5, &key(text("Party")));
+
+    deck.press(3);
+    wait_for_key(&deck, 3, &key(text("Pulse")));
+
+    deck.press(3);
+    wait_for_key(&deck, 3, &key(text("[Pulse]")));
+
+    deck.unplug();
+
+    let (state, result) = menu.join().unwrap();
+
+    assert!(result.is_err());
+    assert!(matches!(
+        state.matrix_animation,
+        MatrixAnimation::Spectrum(SpectrumMode::Pulse)
+    ));
+}
+
+/// Presses the more button on the last key, and waits for the next sheet to be drawn
+fn next_sheet(deck: &VirtualDeck, layout: DeckLayout) {
+    let more = layout.button_count() as u8 - 1;
+    let key = |source| image::resize(&icon(source), layout.key_size.0, layout.key_size.1, true);
+
+    // The keys are drawn in order, so the sheet is done when the more button is
+    deck.send(more, ButtonState::Down);
+    wait_for_key(deck, more, &key(IMG_MORE_DOWN));
+    deck.send(more, ButtonState::Up);
+    wait_for_key(deck, more, &key(IMG_MORE));
+}
+
+#[test]
+fn matrix_page_on_small_decks() {
+    let decks = [
+        (DeckLayout::new(6, (80, 80)), 4),
+        (DeckLayout::new(8, (120, 120)), 3),
+    ];
+
+    for (layout, sheets) in decks {
+        let (width, height) = layout.key_size;
+        let key = |image: RgbImage| image::resize(&image, width, height, true);
+        let more = layout.button_count() as u8 - 1;
+
+        let (deck, menu) = launch_on(layout, super::matrix::launch);
+        wait_for_key(&deck, more, &key(icon(IMG_MORE)));
+
+        // Back stays on the first key of every sheet, the other buttons are spread over them
+        let mut faces = vec![];
+
+        for _ in 0..sheets {
+            assert!(deck.key(0) == key(icon(IMG_BACK)));
+            faces.extend((1..more).map(|index| deck.key(index)));
+
+            next_sheet(&deck, layout);
+        }
+
+        // Past the last sheet is the first one again
+        assert!(deck.key(1) == faces[0]);
+
+        let buttons = faces
+            .iter()
+            .filter(|face| **face != RgbImage::new(width, height))
+            .count();
+        assert_eq!(buttons, 14);
+
+        for source in [
+            IMG_TIMER,
+            IMG_DOUBLE_EMOJI,
+            IMG_EMOJI,
+            IMG_SEQUENCE,
+            IMG_BLOCKS,
+            IMG_FALLING,
+            IMG_EYES,
+            IMG_CLOCK,
+        ] {
+            assert!(faces.contains(&key(icon(source))));
+        }
+
+        // The clock is on the last sheet, it stays on the deck after it is pressed
+        let clock = faces
+            .iter()
+            .position(|face| *face == key(icon(IMG_CLOCK)))
+            .unwrap();
+        let (sheet, index) = (clock / (more as usize - 1), clock % (more as usize - 1) + 1);
+
+        for _ in 0..sheet {
+            next_sheet(&deck, layout);
+        }
+
+        deck.press(index as u8);
+        next_sheet(&deck, layout);
+        assert!(deck.key(1) == faces[0]);
+
+        deck.press(0);
+
+        let (state, result) = menu.join().unwrap();
+        result.unwrap();
+
+        assert!(matches!(
+            state.matrix_animation,
+            MatrixAnimation::Time(ClockFace::Digital)
+        ));
+    }
+}
+
+#[test]
+fn unplugged_deck_ends_menu() {
+    let (deck, menu) = launch(super::camera::launch);
+
+    wait_for_key(&deck, 4, &icon(IMG_CAMERA));
+    deck.unplug();
+
+    let (_, result) = menu.join().unwrap();
+    let error = result.unwrap_err();
+
+    assert_eq!(error.to_string(), "HID communication failure");
+}
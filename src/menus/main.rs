@@ -10,9 +10,20 @@ use std::{
 };
 
 use anyhow::Result;
-use streamdeck_hid_rs::ButtonState;
+use time::OffsetDateTime;
+
+use crate::{
+    deck::{Deck, DeckLayout, GoHome},
+    image::{self, imageops, ImageSourceType, RgbImage},
+    matrix::animations::local_time,
+    render::render_text,
+    AppState,
+};
 
-use crate::{image::ImageSourceType, render::render_text, AppState, Deck};
+use super::{
+    config::MenuConfig,
+    page::{self, Button, Page},
+};
 
 const IMG_CAMERA: ImageSourceType =
     ImageSourceType::Jpeg(include_bytes!("../../images/camera.jpg"));
@@ -29,70 +40,65 @@ const IMG_GAME_DOWN: ImageSourceType =
     ImageSourceType::Jpeg(include_bytes!("../../images/game_down.jpg"));
 
 pub fn launch(state: &mut AppState) -> Result<()> {
-    state.deck.clear()?;
-    state.deck.set_button_image(0, IMG_CAMERA)?;
-    state.deck.set_button_image(1, IMG_MATRIX)?;
-    state.deck.set_button_image(2, IMG_GAME)?;
-
-    let mut task = MainMenuTask::new(state.deck.device());
-    task.start();
+    let mut page = MainPage {
+        task: MainMenuTask::new(state.deck.device()),
+    };
 
     loop {
-        let message = state.deck.next_btn_event()?;
-        let id = message.button_id as u8;
-
-        match id {
-            0 => {
-                if matches!(message.state, ButtonState::Up) {
-                    task.stop();
-
-                    super::camera::launch(state)?;
-
-                    state.deck.clear()?;
-                    state.deck.set_button_image(0, IMG_CAMERA)?;
-                    state.deck.set_button_image(1, IMG_MATRIX)?;
-                    state.deck.set_button_image(2, IMG_GAME)?;
-
-                    task.start();
-                } else {
-                    state.deck.set_button_image(id, IMG_CAMERA_DOWN)?;
-                }
-            }
-
-            1 => {
-                if matches!(message.state, ButtonState::Up) {
-                    task.stop();
+        match page::run(state, &mut page) {
+            // Holding back on any page comes back here, also on this page where it is the camera
+            Err(why) if why.is::<GoHome>() => {}
+            result => return result,
+        }
+    }
+}
 
-                    super::matrix::launch(state)?;
+/// The first page, it never goes back and shows the temperature and the time
+struct MainPage {
+    task: MainMenuTask,
+}
 
-                    state.deck.clear()?;
-                    state.deck.set_button_image(0, IMG_CAMERA)?;
-                    state.deck.set_button_image(1, IMG_MATRIX)?;
-                    state.deck.set_button_image(2, IMG_GAME)?;
-                } else {
-                    state.deck.set_button_image(id, IMG_MATRIX_DOWN)?;
-                }
-            }
+impl Page for MainPage {
+    fn buttons(&self, _state: &AppState) -> Result<Vec<Button<Self>>> {
+        let mut buttons = vec![
+            Button::new(0, IMG_CAMERA)
+                .pressed(IMG_CAMERA_DOWN)
+                .open(|_, state| super::camera::launch(state)),
+            Button::new(1, IMG_MATRIX)
+                .pressed(IMG_MATRIX_DOWN)
+                .open(|_, state| super::matrix::launch(state)),
+            Button::new(2, IMG_GAME)
+                .pressed(IMG_GAME_DOWN)
+                .open(|_, state| super::games::launch(state)),
+            Button::new(3, ImageSourceType::Rgb(render_text("Music", 16)?))
+                .open(|_, state| super::music::launch(state)),
+            Button::new(4, ImageSourceType::Rgb(render_text("Sprites", 16)?))
+                .open(|_, state| super::sprites::launch(state)),
+        ];
+
+        // The pages from `menus.toml`, on the second row
+        for (key, config) in (5..).zip(MenuConfig::load().pages) {
+            let label = render_text(&config.label, 16)?;
+
+            buttons.push(
+                Button::new(key, ImageSourceType::Rgb(label))
+                    .open(move |_, state| super::custom::launch(state, config.clone())),
+            );
+        }
 
-            2 => {
-                if matches!(message.state, ButtonState::Up) {
-                    task.stop();
+        Ok(buttons)
+    }
 
-                    super::games::launch(state)?;
+    fn shown(&mut self, _state: &mut AppState) -> Result<()> {
+        self.task.start();
 
-                    state.deck.clear()?;
-                    state.deck.set_button_image(0, IMG_CAMERA)?;
-                    state.deck.set_button_image(1, IMG_MATRIX)?;
-                    state.deck.set_button_image(2, IMG_GAME)?;
+        Ok(())
+    }
 
-                    task.start();
-                } else {
-                    state.deck.set_button_image(id, IMG_GAME_DOWN)?;
-                }
-            }
+    fn hidden(&mut self, _state: &mut AppState) -> Result<()> {
+        self.task.stop();
 
-            _ => {}
-        }
+        Ok(())
     }
 }
 
@@ -109,7 +115,10 @@ impl MainMenuTask {
         }
     }
 
+    /// Starts drawing the keys, a task that is still running is stopped first
     pub fn start(&mut self) {
+        self.stop();
+
         self.signal = Arc::new(AtomicBool::new(false));
         self.signal.store(false, Ordering::Relaxed);
 
@@ -123,8 +132,8 @@ impl MainMenuTask {
                 let mut last_t = 0;
 
                 while !signal.load(Ordering::Relaxed) {
-                    let local = time::OffsetDateTime::now_utc();
-                    let (h, m) = (local.hour(), local.minute());
+                    let now = local_time(OffsetDateTime::now_utc());
+                    let (h, m) = (now.hour(), now.minute());
 
                     if let Ok(mut file) = File::open("/sys/class/thermal/thermal_zone0/temp") {
                         let mut temp = String::new();
@@ -144,9 +153,6 @@ impl MainMenuTask {
                         }
                     }
 
-                    // Timezone shenanigans
-                    let h = if (1..10).contains(&h) { h + 1 } else { h + 2 } % 24;
-
                     if h == last_h && m == last_m {
                         sleep(Duration::from_secs(1));
                         continue;
@@ -178,3 +184,25 @@ impl MainMenuTask {
         self.signal.store(true, Ordering::Relaxed);
     }
 }
+
+/// The time in the middle of all keys, for while the deck sleeps
+pub fn clock_screensaver(layout: DeckLayout) -> Result<RgbImage> {
+    let now = local_time(OffsetDateTime::now_utc());
+    let (h, m) = (now.hour(), now.minute());
+    let (width, height) = layout.screen_size();
+
+    let mut time = render_text(format!("{h:0>2}:{m:0>2}"), 64)?;
+
+    if time.width() > width {
+        let scaled = time.height() * width / time.width();
+        time = image::resize(&time, width, scaled, true);
+    }
+
+    let mut screen = RgbImage::new(width, height);
+    let x = (width - time.width()) / 2;
+    let y = height.saturating_sub(time.height()) / 2;
+
+    imageops::overlay(&mut screen, &time, x as i64, y as i64);
+
+    Ok(screen)
+}
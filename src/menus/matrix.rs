@@ -1,18 +1,31 @@
 mod double_emoji;
-mod emoji;
-
-use crate::{image::ImageSourceType, state::MatrixAnimation, AppState};
+pub mod emoji;
+mod eyes;
+mod timer;
+
+use crate::{
+    image::ImageSourceType,
+    matrix::animations::{self, ClockFace, ParticlePreset},
+    render::render_text,
+    state::{Effect, MatrixAnimation, Mix},
+    AppState,
+};
 use anyhow::Result;
-use streamdeck_hid_rs::ButtonState;
 
-const IMG_BACK: ImageSourceType = ImageSourceType::Jpeg(include_bytes!("../../images/back.jpg"));
-const IMG_BACK_DOWN: ImageSourceType =
-    ImageSourceType::Jpeg(include_bytes!("../../images/back_down.jpg"));
+use super::{
+    config::{ButtonAction, ButtonConfig, MenuConfig},
+    custom::ConfigButton,
+    page::{self, Button, Page},
+};
 
 const IMG_EMOJI: ImageSourceType = ImageSourceType::Jpeg(include_bytes!("../../images/emoji.jpg"));
 const IMG_EMOJI_DOWN: ImageSourceType =
     ImageSourceType::Jpeg(include_bytes!("../../images/emoji_down.jpg"));
 
+const IMG_TIMER: ImageSourceType = ImageSourceType::Jpeg(include_bytes!("../../images/timer.jpg"));
+const IMG_TIMER_DOWN: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/timer_down.jpg"));
+
 const IMG_DOUBLE_EMOJI: ImageSourceType =
     ImageSourceType::Jpeg(include_bytes!("../../images/double_emoji.jpg"));
 const IMG_DOUBLE_EMOJI_DOWN: ImageSourceType =
@@ -44,142 +57,204 @@ const IMG_SEQUENCE_DOWN: ImageSourceType =
     ImageSourceType::Jpeg(include_bytes!("../../images/sequence_down.jpg"));
 
 pub fn launch(state: &mut AppState) -> Result<()> {
-    state.deck.clear()?;
-
-    state.deck.set_button_image(0, IMG_BACK)?;
-    state.deck.set_button_image(3, IMG_DOUBLE_EMOJI)?;
-    state.deck.set_button_image(4, IMG_EMOJI)?;
-
-    render_animation_items(state)?;
-
-    loop {
-        let message = state.deck.next_btn_event()?;
-        let id = message.button_id as u8;
-
-        match id {
-            0 => {
-                if matches!(message.state, ButtonState::Up) {
-                    break;
-                } else {
-                    state
-                        .deck
-                        .set_button_image(message.button_id as u8, IMG_BACK_DOWN)?;
-                }
-            }
-
-            3 => {
-                if matches!(message.state, ButtonState::Up) {
-                    state.matrix.clear()?;
+    let animations = MenuConfig::load()
+        .matrix
+        .into_iter()
+        .map(|config| load_button(state, config))
+        .collect::<Result<_>>()?;
 
-                    double_emoji::launch(state)?;
+    page::run(state, &mut MatrixPage { animations })
+}
 
-                    state.start_matrix_animation();
+/// The built-in buttons, and the animations from `menus.toml` on the keys they leave free
+struct MatrixPage {
+    animations: Vec<ConfigButton>,
+}
 
-                    state.deck.clear()?;
-                    state.deck.flush_btn_events()?;
-                    state.deck.set_button_image(0, IMG_BACK)?;
-                    state.deck.set_button_image(3, IMG_DOUBLE_EMOJI)?;
-                    state.deck.set_button_image(4, IMG_EMOJI)?;
+/// A button from `menus.toml`, animations that have a built-in icon show it when the button has
+/// no face of its own
+fn load_button(state: &AppState, config: ButtonConfig) -> Result<ConfigButton> {
+    let icons = match &config.action {
+        ButtonAction::Animation(MatrixAnimation::Sequence) => {
+            Some((IMG_SEQUENCE, IMG_SEQUENCE_DOWN))
+        }
+        ButtonAction::Animation(MatrixAnimation::Blocks) => Some((IMG_BLOCKS, IMG_BLOCKS_DOWN)),
+        ButtonAction::Animation(MatrixAnimation::Falling) => Some((IMG_FALLING, IMG_FALLING_DOWN)),
+        ButtonAction::Animation(MatrixAnimation::Time(_)) => Some((IMG_CLOCK, IMG_CLOCK_DOWN)),
+        ButtonAction::Animation(MatrixAnimation::Eyes) => Some((IMG_EYES, IMG_EYES_DOWN)),
+        _ => None,
+    };
+
+    match icons {
+        Some((icon, pressed)) if !config.has_face() => {
+            Ok(ConfigButton::with_icon(config, icon, pressed))
+        }
+        _ => ConfigButton::load(state, config),
+    }
+}
 
-                    render_animation_items(state)?;
-                } else {
-                    state.deck.set_button_image(3, IMG_DOUBLE_EMOJI_DOWN)?;
+impl Page for MatrixPage {
+    fn buttons(&self, state: &AppState) -> Result<Vec<Button<Self>>> {
+        let text = |label: &str, size| -> Result<ImageSourceType<'static>> {
+            Ok(ImageSourceType::Rgb(render_text(label, size)?))
+        };
+
+        let script = match &state.matrix_animation {
+            MatrixAnimation::Script(path) => path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().chars().take(8).collect())
+                .unwrap_or_default(),
+            _ => "Script".to_string(),
+        };
+
+        let effect = match &state.matrix_animation {
+            MatrixAnimation::Effect(effect) => effect.name(),
+            _ => "Effect",
+        };
+
+        let particles = match &state.matrix_animation {
+            MatrixAnimation::Particles(preset) => preset.name(),
+            _ => "Fx",
+        };
+
+        let mix = match &state.matrix_animation {
+            MatrixAnimation::Mix(mix) => mix.name(),
+            _ => "Mix",
+        };
+
+        // Pressing it again while the eyes are shown opens their controls
+        let eyes = Button::new(13, IMG_EYES).pressed(IMG_EYES_DOWN);
+        let eyes = if matches!(
+            state.matrix_animation,
+            MatrixAnimation::Eyes | MatrixAnimation::TalkingFace
+        ) {
+            eyes.open(|_, state| eyes::launch(state))
+        } else {
+            eyes.run(|_, state| show(state, MatrixAnimation::Eyes))
+        };
+
+        let mut buttons = vec![
+            Button::back(),
+            Button::new(1, text(&script, 16)?).run(|_, state| {
+                // Scripts are listed again on every press, so new ones show up without a restart
+                let scripts = animations::list_scripts();
+
+                let next = match &state.matrix_animation {
+                    MatrixAnimation::Script(current) => scripts
+                        .iter()
+                        .position(|path| path == current)
+                        .map(|idx| (idx + 1) % scripts.len())
+                        .unwrap_or(0),
+                    _ => 0,
+                };
+
+                match scripts.get(next) {
+                    Some(path) => show(state, MatrixAnimation::Script(path.clone())),
+                    None => Ok(()),
                 }
-            }
-
-            4 => {
-                if matches!(message.state, ButtonState::Up) {
-                    state.matrix.clear()?;
-
-                    emoji::launch(state)?;
+            }),
+            Button::new(2, IMG_TIMER)
+                .pressed(IMG_TIMER_DOWN)
+                .open(|_, state| {
+                    // Also when back is held to go home
+                    let result = timer::launch(state);
 
                     state.start_matrix_animation();
 
-                    state.deck.clear()?;
-                    state.deck.flush_btn_events()?;
-                    state.deck.set_button_image(0, IMG_BACK)?;
-                    state.deck.set_button_image(3, IMG_DOUBLE_EMOJI)?;
-                    state.deck.set_button_image(4, IMG_EMOJI)?;
-
-                    render_animation_items(state)?;
-                } else {
-                    state.deck.set_button_image(4, IMG_EMOJI_DOWN)?;
-                }
-            }
-
-            10 => {
-                if matches!(message.state, ButtonState::Up) {
-                    state.deck.set_button_image(10, IMG_SEQUENCE)?;
-
-                    state.matrix_animation = MatrixAnimation::Sequence;
-                    state.start_matrix_animation();
-                } else {
-                    state.deck.set_button_image(10, IMG_SEQUENCE_DOWN)?;
-                }
-            }
+                    result
+                }),
+            Button::new(3, IMG_DOUBLE_EMOJI)
+                .pressed(IMG_DOUBLE_EMOJI_DOWN)
+                .open(|_, state| {
+                    state.matrix.clear()?;
 
-            11 => {
-                if matches!(message.state, ButtonState::Up) {
-                    state.deck.set_button_image(11, IMG_BLOCKS)?;
+                    let result = double_emoji::launch(state);
 
-                    state.matrix_animation = MatrixAnimation::Blocks;
                     state.start_matrix_animation();
-                } else {
-                    state.deck.set_button_image(11, IMG_BLOCKS_DOWN)?;
-                }
-            }
-
-            12 => {
-                if matches!(message.state, ButtonState::Up) {
-                    state.deck.set_button_image(12, IMG_FALLING)?;
 
-                    state.matrix_animation = MatrixAnimation::Falling;
-                    state.start_matrix_animation();
-                } else {
-                    state.deck.set_button_image(12, IMG_FALLING_DOWN)?;
-                }
-            }
+                    result
+                }),
+            Button::new(4, IMG_EMOJI)
+                .pressed(IMG_EMOJI_DOWN)
+                .open(|_, state| {
+                    state.matrix.clear()?;
 
-            13 => {
-                if matches!(message.state, ButtonState::Up) {
-                    state.deck.set_button_image(13, IMG_EYES)?;
+                    let result = emoji::launch(state);
 
-                    state.matrix_animation = MatrixAnimation::Eyes;
                     state.start_matrix_animation();
-                } else {
-                    state.deck.set_button_image(13, IMG_EYES_DOWN)?;
-                }
-            }
-
-            14 => {
-                if matches!(message.state, ButtonState::Up) {
-                    state.deck.set_button_image(14, IMG_CLOCK)?;
 
+                    result
+                }),
+            Button::new(5, text(state.palette.name(), 12)?).run(|_, state| {
+                state.palette = state.palette.next();
+                state.start_matrix_animation();
+
+                Ok(())
+            }),
+            Button::new(6, text(&format!("{}x", state.speed), 16)?).run(|_, state| {
+                state.speed = next_speed(state.speed);
+                state.start_matrix_animation();
+
+                Ok(())
+            }),
+            Button::new(7, text(effect, 16)?).run(|_, state| {
+                let animation = match state.matrix_animation {
+                    MatrixAnimation::Effect(effect) => MatrixAnimation::Effect(effect.next()),
+                    _ => MatrixAnimation::Effect(Effect::Plasma),
+                };
+
+                show(state, animation)
+            }),
+            Button::new(8, text(particles, 16)?).run(|_, state| {
+                let animation = match state.matrix_animation {
+                    MatrixAnimation::Particles(preset) => MatrixAnimation::Particles(preset.next()),
+                    _ => MatrixAnimation::Particles(ParticlePreset::Fireworks),
+                };
+
+                show(state, animation)
+            }),
+            Button::new(9, text(mix, 16)?).run(|_, state| {
+                let animation = match state.matrix_animation {
+                    MatrixAnimation::Mix(mix) => MatrixAnimation::Mix(mix.next()),
+                    _ => MatrixAnimation::Mix(Mix::RainClock),
+                };
+
+                show(state, animation)
+            }),
+            eyes,
+            Button::new(14, IMG_CLOCK)
+                .pressed(IMG_CLOCK_DOWN)
+                .run(|_, state| {
+                    // Cycle through the clock faces, starting at the regular digital clock
                     let animation = match state.matrix_animation {
-                        MatrixAnimation::Time => MatrixAnimation::TimeNoClock,
-                        _ => MatrixAnimation::Time,
+                        MatrixAnimation::Time(face) => MatrixAnimation::Time(face.next()),
+                        _ => MatrixAnimation::Time(ClockFace::Digital),
                     };
 
-                    state.matrix_animation = animation;
-                    state.start_matrix_animation();
-                } else {
-                    state.deck.set_button_image(14, IMG_CLOCK_DOWN)?;
-                }
-            }
+                    show(state, animation)
+                }),
+        ];
 
-            _ => {}
-        }
-    }
+        buttons.extend(self.animations.iter().map(ConfigButton::button));
 
-    Ok(())
+        Ok(buttons)
+    }
 }
 
-fn render_animation_items(state: &mut AppState) -> Result<()> {
-    state.deck.set_button_image(14, IMG_CLOCK)?;
-    state.deck.set_button_image(13, IMG_EYES)?;
-    state.deck.set_button_image(12, IMG_FALLING)?;
-    state.deck.set_button_image(11, IMG_BLOCKS)?;
-    state.deck.set_button_image(10, IMG_SEQUENCE)?;
+fn show(state: &mut AppState, animation: MatrixAnimation) -> Result<()> {
+    state.matrix_animation = animation;
+    state.start_matrix_animation();
 
     Ok(())
 }
+
+/// Cycles between half, normal and double speed
+fn next_speed(speed: f32) -> f32 {
+    if speed < 1.0 {
+        1.0
+    } else if speed < 2.0 {
+        2.0
+    } else {
+        0.5
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{
+    image::ImageSourceType,
+    matrix::animations::{Gaze, Mood},
+    menus::page::{self, Button, Page},
+    render::render_text,
+    state::AppState,
+};
+use anyhow::Result;
+
+// Eyes layout:
+
+// |-------|-------|-------|-------|-------|
+// |       |       |       |       |       |
+// | BACK  | HAPPY | ANGRY |SLEEPY |  WOW  |
+// |       |       |       |       |       |
+// |-------|-------|-------|-------|-------|
+// |       |       |       |       |       |
+// | LOVE  |NEUTRAL|       |       | IDLE  |
+// |       |       |       |       |       |
+// |-------|-------|-------|-------|-------|
+// |       |       |       |       |       |
+// | LEFT  | APART |CENTER | CROSS | RIGHT |
+// |       |       |       |       |       |
+// |-------|-------|-------|-------|-------|
+
+const MOODS: [(u8, Mood); 6] = [
+    (1, Mood::Happy),
+    (2, Mood::Angry),
+    (3, Mood::Sleepy),
+    (4, Mood::Surprised),
+    (5, Mood::Love),
+    (6, Mood::Neutral),
+];
+
+const GAZES: [(u8, Gaze); 5] = [
+    (10, Gaze::Left),
+    (11, Gaze::Apart),
+    (12, Gaze::Center),
+    (13, Gaze::Crossed),
+    (14, Gaze::Right),
+];
+
+pub fn launch(state: &mut AppState) -> Result<()> {
+    page::run(state, &mut EyesPage)
+}
+
+struct EyesPage;
+
+impl Page for EyesPage {
+    fn buttons(&self, state: &AppState) -> Result<Vec<Button<Self>>> {
+        let mut buttons = vec![Button::back()];
+
+        for (id, mood) in MOODS {
+            let image = ImageSourceType::Rgb(render_text(mood.name(), 16)?);
+
+            buttons.push(Button::new(id, image).run(move |_, state| {
+                state.eyes.express(mood);
+
+                Ok(())
+            }));
+        }
+
+        for (id, gaze) in GAZES {
+            let image = ImageSourceType::Rgb(render_text(gaze.name(), 16)?);
+
+            buttons.push(Button::new(id, image).run(move |_, state| {
+                state.eyes.look(gaze);
+
+                Ok(())
+            }));
+        }
+
+        let idle = if state.eyes.is_idle() {
+            "Idle on"
+        } else {
+            "Idle off"
+        };
+
+        buttons.push(
+            Button::new(9, ImageSourceType::Rgb(render_text(idle, 14)?)).run(|_, state| {
+                state.eyes.set_idle(!state.eyes.is_idle());
+
+                Ok(())
+            }),
+        );
+
+        Ok(buttons)
+    }
+}
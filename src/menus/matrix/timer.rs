@@ -0,0 +1,219 @@
+use std::time::Duration;
+
+use time::{OffsetDateTime, Time};
+
+use crate::{
+    image::ImageSourceType,
+    matrix::{
+        animations::{
+            local_time, Animation, BlocksAnimation, CountdownAnimation, Deadline, EyesAnimation,
+            FallingAnimation, StopwatchAnimation, Timer,
+        },
+        color_utils::Palette,
+    },
+    menus::page::{self, Button, Page},
+    render::render_text,
+    state::AppState,
+};
+use anyhow::Result;
+
+use super::{IMG_BLOCKS, IMG_BLOCKS_DOWN, IMG_EYES, IMG_EYES_DOWN, IMG_FALLING, IMG_FALLING_DOWN};
+
+// Timer layout:
+
+// |-------|-------|-------|-------|-------|
+// |       |       |       |       |       |
+// | BACK  | MODE  |FINALE | START | RESET |
+// |       |       |       | PAUSE |       |
+// |-------|-------|-------|-------|-------|
+// |       |       |       |       |       |
+// |       |       |       |       |       |
+// |       |       |       |       |       |
+// |-------|-------|-------|-------|-------|
+// |       |       |       |       |       |
+// | MIDN. | 1 MIN | 5 MIN |10 MIN | 1 HR  |
+// |       |       |       |       |       |
+// |-------|-------|-------|-------|-------|
+
+enum Mode {
+    Countdown,
+    Stopwatch,
+}
+
+#[derive(Clone, Copy)]
+enum Finale {
+    Falling,
+    Blocks,
+    Eyes,
+}
+
+impl Finale {
+    fn advance(&mut self) {
+        match self {
+            Self::Falling => *self = Self::Blocks,
+            Self::Blocks => *self = Self::Eyes,
+            Self::Eyes => *self = Self::Falling,
+        }
+    }
+
+    fn image(&self, down: bool) -> ImageSourceType<'static> {
+        match (self, down) {
+            (Self::Falling, true) => IMG_FALLING_DOWN,
+            (Self::Falling, false) => IMG_FALLING,
+            (Self::Blocks, true) => IMG_BLOCKS_DOWN,
+            (Self::Blocks, false) => IMG_BLOCKS,
+            (Self::Eyes, true) => IMG_EYES_DOWN,
+            (Self::Eyes, false) => IMG_EYES,
+        }
+    }
+
+    fn animation(&self, palette: Palette) -> Box<dyn Animation + Send + Sync> {
+        match self {
+            Self::Falling => Box::new(FallingAnimation::new(palette)),
+            Self::Blocks => Box::new(BlocksAnimation::new(palette)),
+            Self::Eyes => Box::new(EyesAnimation::new()),
+        }
+    }
+}
+
+enum Preset {
+    Midnight,
+    Minutes(u64),
+}
+
+impl Preset {
+    fn label(&self) -> String {
+        match self {
+            Self::Midnight => "0:00".to_string(),
+            Self::Minutes(60) => "1h".to_string(),
+            Self::Minutes(minutes) => format!("{minutes}m"),
+        }
+    }
+
+    /// Midnight is the next one from now, the others start counting when the timer starts
+    fn deadline(&self) -> Deadline {
+        match self {
+            Self::Midnight => {
+                let now = OffsetDateTime::now_utc();
+                let today = local_time(now).time() - Time::MIDNIGHT;
+
+                Deadline::At(now + (time::Duration::DAY - today))
+            }
+            Self::Minutes(minutes) => Deadline::After(Duration::from_secs(minutes * 60)),
+        }
+    }
+}
+
+const PRESETS: [(u8, Preset); 5] = [
+    (10, Preset::Midnight),
+    (11, Preset::Minutes(1)),
+    (12, Preset::Minutes(5)),
+    (13, Preset::Minutes(10)),
+    (14, Preset::Minutes(60)),
+];
+
+pub fn launch(state: &mut AppState) -> Result<()> {
+    let mut page = TimerPage {
+        timer: Timer::new(),
+        mode: Mode::Countdown,
+        finale: Finale::Falling,
+        target: PRESETS[0].1.deadline(),
+    };
+
+    page.start_animation(state)?;
+
+    page::run(state, &mut page)
+}
+
+struct TimerPage {
+    timer: Timer,
+    mode: Mode,
+    finale: Finale,
+    target: Deadline,
+}
+
+impl TimerPage {
+    fn start_animation(&self, state: &AppState) -> Result<()> {
+        let animation: Box<dyn Animation + Send + Sync> = match self.mode {
+            Mode::Countdown => Box::new(CountdownAnimation::new(
+                self.timer.clone(),
+                self.target,
+                self.finale.animation(state.palette),
+            )),
+            Mode::Stopwatch => Box::new(StopwatchAnimation::new(self.timer.clone())),
+        };
+
+        state.matrix.set_animation(animation)
+    }
+}
+
+impl Page for TimerPage {
+    fn buttons(&self, _state: &AppState) -> Result<Vec<Button<Self>>> {
+        let text = |label: &str, size| -> Result<ImageSourceType<'static>> {
+            Ok(ImageSourceType::Rgb(render_text(label, size)?))
+        };
+
+        let mode = match self.mode {
+            Mode::Countdown => "Count",
+            Mode::Stopwatch => "Watch",
+        };
+
+        let start_pause = if self.timer.is_running() {
+            "Pause"
+        } else {
+            "Start"
+        };
+
+        let mut buttons = vec![
+            Button::back(),
+            Button::new(1, text(mode, 20)?).run(|page: &mut Self, state| {
+                page.mode = match page.mode {
+                    Mode::Countdown => Mode::Stopwatch,
+                    Mode::Stopwatch => Mode::Countdown,
+                };
+
+                page.timer.reset();
+                page.start_animation(state)
+            }),
+            Button::new(3, text(start_pause, 20)?).run(|page: &mut Self, _| {
+                if page.timer.is_running() {
+                    page.timer.pause();
+                } else {
+                    page.timer.start();
+                }
+
+                Ok(())
+            }),
+            Button::new(4, text("Reset", 20)?).run(|page: &mut Self, _| {
+                page.timer.reset();
+
+                Ok(())
+            }),
+        ];
+
+        if matches!(self.mode, Mode::Countdown) {
+            buttons.push(
+                Button::new(2, self.finale.image(false))
+                    .pressed(self.finale.image(true))
+                    .run(|page: &mut Self, state| {
+                        page.finale.advance();
+                        page.start_animation(state)
+                    }),
+            );
+
+            for (idx, (key, preset)) in PRESETS.iter().enumerate() {
+                buttons.push(Button::new(*key, text(&preset.label(), 24)?).run(
+                    move |page: &mut Self, state| {
+                        // Picking a preset stops the countdown, it has to be started again from
+                        // the deck
+                        page.target = PRESETS[idx].1.deadline();
+                        page.timer.reset();
+                        page.start_animation(state)
+                    },
+                ));
+            }
+        }
+
+        Ok(buttons)
+    }
+}
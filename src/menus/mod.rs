@@ -1,4 +1,11 @@
 pub mod camera;
+mod config;
+mod custom;
+#[cfg(test)]
+mod flows;
 pub mod games;
 pub mod main;
 pub mod matrix;
+pub mod music;
+mod page;
+pub mod sprites;
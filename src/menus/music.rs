@@ -0,0 +1,62 @@
+use crate::{
+    image::ImageSourceType, matrix::animations::SpectrumMode, render::render_text,
+    state::MatrixAnimation, AppState,
+};
+use anyhow::Result;
+
+use super::page::{self, Button, Page};
+
+const MODES: [(u8, SpectrumMode); 3] = [
+    (1, SpectrumMode::Bars),
+    (2, SpectrumMode::Meter),
+    (3, SpectrumMode::Pulse),
+];
+
+pub fn launch(state: &mut AppState) -> Result<()> {
+    page::run(state, &mut MusicPage)
+}
+
+struct MusicPage;
+
+impl Page for MusicPage {
+    /// The visualizer modes and the talking face, the one that is currently running in brackets
+    fn buttons(&self, state: &AppState) -> Result<Vec<Button<Self>>> {
+        let mut buttons = vec![Button::back()];
+
+        for (id, mode) in MODES {
+            let label = match state.matrix_animation {
+                MatrixAnimation::Spectrum(current) if current == mode => {
+                    format!("[{}]", mode.name())
+                }
+                _ => mode.name().to_string(),
+            };
+
+            buttons.push(
+                Button::new(id, ImageSourceType::Rgb(render_text(label, 16)?)).run(
+                    move |_, state| {
+                        state.matrix_animation = MatrixAnimation::Spectrum(mode);
+                        state.start_matrix_animation();
+
+                        Ok(())
+                    },
+                ),
+            );
+        }
+
+        let label = match state.matrix_animation {
+            MatrixAnimation::TalkingFace => "[Face]",
+            _ => "Face",
+        };
+
+        buttons.push(
+            Button::new(4, ImageSourceType::Rgb(render_text(label, 16)?)).run(|_, state| {
+                state.matrix_animation = MatrixAnimation::TalkingFace;
+                state.start_matrix_animation();
+
+                Ok(())
+            }),
+        );
+
+        Ok(buttons)
+    }
+}
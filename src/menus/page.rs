@@ -0,0 +1,277 @@
+use anyhow::Result;
+
+use crate::{
+    deck::{DeckLayout, GestureKind, KeyMap, MENU_COLUMNS, MENU_ROWS},
+    image::ImageSourceType,
+    AppState,
+};
+
+const IMG_BACK: ImageSourceType = ImageSourceType::Jpeg(include_bytes!("../../images/back.jpg"));
+const IMG_BACK_DOWN: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/back_down.jpg"));
+
+const IMG_MORE: ImageSourceType = ImageSourceType::Jpeg(include_bytes!("../../images/next.jpg"));
+const IMG_MORE_DOWN: ImageSourceType =
+    ImageSourceType::Jpeg(include_bytes!("../../images/next_down.jpg"));
+
+/// The menu key of the button that shows the next sheet of a page, outside of the menu grid so
+/// it is never the key of another button
+const MORE_KEY: u8 = MENU_COLUMNS * MENU_ROWS;
+
+type Callback<P> = Box<dyn Fn(&mut P, &mut AppState) -> Result<()>>;
+
+/// What a button does when it is pressed
+pub enum Action<P> {
+    /// Nothing, the button only shows something
+    None,
+
+    /// Leaves the page, to the page that opened it
+    Back,
+
+    /// Changes the page or the app, the buttons are drawn again afterwards
+    Run(Callback<P>),
+
+    /// Opens a sub-page, this page is drawn again when it is left
+    Open(Callback<P>),
+}
+
+/// A key on a [`Page`]
+pub struct Button<P> {
+    pub key: u8,
+    pub icon: ImageSourceType<'static>,
+
+    /// Shown while the key is held down
+    pub pressed: Option<ImageSourceType<'static>>,
+    pub action: Action<P>,
+}
+
+impl<P> Button<P> {
+    pub fn new(key: u8, icon: ImageSourceType<'static>) -> Self {
+        Self {
+            key,
+            icon,
+            pressed: None,
+            action: Action::None,
+        }
+    }
+
+    /// The back button on key 0, that every page has
+    pub fn back() -> Self {
+        Self::new(0, IMG_BACK)
+            .pressed(IMG_BACK_DOWN)
+            .action(Action::Back)
+    }
+
+    pub fn pressed(mut self, icon: ImageSourceType<'static>) -> Self {
+        self.pressed = Some(icon);
+        self
+    }
+
+    pub fn action(mut self, action: Action<P>) -> Self {
+        self.action = action;
+        self
+    }
+
+    pub fn run(self, run: impl Fn(&mut P, &mut AppState) -> Result<()> + 'static) -> Self {
+        self.action(Action::Run(Box::new(run)))
+    }
+
+    pub fn open(self, open: impl Fn(&mut P, &mut AppState) -> Result<()> + 'static) -> Self {
+        self.action(Action::Open(Box::new(open)))
+    }
+}
+
+/// A menu page, that is made of the buttons it declares
+///
+/// [`run`] shows the page, draws pressed buttons and runs their actions. The buttons are asked
+/// for again after every action, so they can change with the page and the app.
+///
+/// Buttons are placed on the 5x3 menu grid. On decks that don't have a key for all of them,
+/// they are shown in order on sheets of as many as fit, with a more button on the last key.
+pub trait Page: Sized {
+    fn buttons(&self, state: &AppState) -> Result<Vec<Button<Self>>>;
+
+    /// Called when the page is on the deck, when it opens and when a sub-page is left
+    fn shown(&mut self, _state: &mut AppState) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when the page is no longer on the deck, before a sub-page opens and when the
+    /// page is left
+    fn hidden(&mut self, _state: &mut AppState) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Shows `page` until its back button is pressed
+///
+/// Sub-pages are run from the actions of this page, so the pages that are open stack up and
+/// every page is left to the one below it. Holding back leaves all of them, see
+/// [`crate::deck::GoHome`]. The page is hidden when it is left, also when that is by an error.
+pub fn run<P: Page>(state: &mut AppState, page: &mut P) -> Result<()> {
+    let mut visible = false;
+    let result = run_visible(state, page, &mut visible);
+
+    if !visible {
+        return result;
+    }
+
+    // The error that left the page is passed on, not one of hiding it
+    let hidden = page.hidden(state);
+
+    result.and(hidden)
+}
+
+/// Runs the page until back is pressed, `visible` tells whether it is still to be hidden
+fn run_visible<P: Page>(state: &mut AppState, page: &mut P, visible: &mut bool) -> Result<()> {
+    let mut sheet = 0;
+    let mut buttons = show(state, page, &mut sheet)?;
+    *visible = true;
+
+    loop {
+        let gesture = state.deck.next_gesture()?;
+
+        let Some(button) = buttons
+            .iter()
+            .find(|button| button.key == gesture.button_id)
+        else {
+            continue;
+        };
+
+        match (gesture.kind, &button.action) {
+            (GestureKind::Down, _) => {
+                if let Some(pressed) = &button.pressed {
+                    state.deck.set_button_image(button.key, pressed.clone())?;
+                }
+            }
+
+            (GestureKind::Press, _) if button.key == MORE_KEY => {
+                sheet += 1;
+
+                buttons = draw(state, page, &mut sheet)?;
+            }
+
+            (GestureKind::Press, Action::Back) => break,
+
+            (GestureKind::Press, Action::Run(run)) => {
+                run(page, state)?;
+
+                buttons = draw(state, page, &mut sheet)?;
+            }
+
+            (GestureKind::Press, Action::Open(open)) => {
+                *visible = false;
+                page.hidden(state)?;
+
+                // Pages that aren't made of buttons address the keys on the menu grid
+                let layout = state.deck.layout();
+                state.deck.set_key_map(KeyMap::grid(layout));
+
+                open(page, state)?;
+
+                buttons = show(state, page, &mut sheet)?;
+                *visible = true;
+            }
+
+            // Held too long or together with another key, that doesn't press the buttons
+            (kind, _) => {
+                let mut keys = vec![button.key];
+
+                if let GestureKind::Chord(other) = kind {
+                    keys.push(other);
+                }
+
+                for button in buttons.iter().filter(|button| keys.contains(&button.key)) {
+                    state
+                        .deck
+                        .set_button_image(button.key, button.icon.clone())?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Draws sheet `sheet` of the page on an empty deck
+fn show<P: Page>(state: &mut AppState, page: &mut P, sheet: &mut usize) -> Result<Vec<Button<P>>> {
+    // Presses from before the page was opened were meant for the page that was on the deck
+    state.deck.flush_btn_events()?;
+
+    let buttons = draw(state, page, sheet)?;
+
+    page.shown(state)?;
+
+    Ok(buttons)
+}
+
+/// Draws the buttons of sheet `sheet`, and clears the keys that no longer have a button
+///
+/// Past the last sheet is the first one again. The buttons that are returned include the more
+/// button, when there is more than one sheet. Presses that come in while the page is drawn are
+/// kept, they are for this page too.
+fn draw<P: Page>(state: &AppState, page: &P, sheet: &mut usize) -> Result<Vec<Button<P>>> {
+    let mut buttons = page.buttons(state)?;
+    let sheets = sheets(state.deck.layout(), &buttons);
+
+    *sheet %= sheets.len();
+
+    if sheets.len() > 1 {
+        buttons.push(Button::new(MORE_KEY, IMG_MORE).pressed(IMG_MORE_DOWN));
+    }
+
+    state.deck.set_key_map(sheets[*sheet].clone());
+    state.deck.set_page(
+        buttons
+            .iter()
+            .map(|button| (button.key, button.icon.clone()))
+            .collect(),
+    )?;
+
+    Ok(buttons)
+}
+
+/// Where the buttons go on `layout`, for every sheet of the page
+///
+/// Buttons that all have a key on the menu grid stay there. Otherwise key 0, that goes home
+/// when it is held, stays in the corner and the other buttons follow it in order of their keys.
+/// When they need more than one sheet, the last key of every sheet is the more button.
+fn sheets<P>(layout: DeckLayout, buttons: &[Button<P>]) -> Vec<KeyMap> {
+    let grid = KeyMap::grid(layout);
+
+    if buttons
+        .iter()
+        .all(|button| grid.device_key(button.key).is_some())
+    {
+        return vec![grid];
+    }
+
+    let mut keys = buttons
+        .iter()
+        .map(|button| button.key)
+        .filter(|key| *key != 0)
+        .collect::<Vec<_>>();
+    keys.sort();
+
+    let count = layout.button_count();
+
+    if keys.len() < count {
+        let keys = [Some(0)].into_iter().chain(keys.into_iter().map(Some));
+
+        return vec![KeyMap::new(layout, keys.collect())];
+    }
+
+    // All keys but the first and the last one
+    let per_sheet = count.saturating_sub(2).max(1);
+
+    keys.chunks(per_sheet)
+        .map(|sheet| {
+            let mut keys = vec![Some(0)];
+            keys.extend(sheet.iter().copied().map(Some));
+            keys.resize(count - 1, None);
+            keys.push(Some(MORE_KEY));
+
+            KeyMap::new(layout, keys)
+        })
+        .collect()
+}
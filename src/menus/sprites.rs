@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use crate::{
+    image::ImageSourceType, matrix::animations, render::render_text, state::MatrixAnimation,
+    AppState,
+};
+use anyhow::Result;
+
+use super::page::{self, Button, Page};
+
+// Every key after the back button plays one sprite tag
+const MAX_SPRITES: usize = 14;
+
+pub fn launch(state: &mut AppState) -> Result<()> {
+    // Sprites are listed when the page opens, so new files show up without a restart
+    let sprites = animations::list_sprites()
+        .into_iter()
+        .take(MAX_SPRITES)
+        .collect::<Vec<_>>();
+
+    page::run(state, &mut SpritesPage { sprites })
+}
+
+struct SpritesPage {
+    sprites: Vec<(PathBuf, Option<String>)>,
+}
+
+impl Page for SpritesPage {
+    /// Shows the tag names, or the file name for files without tags, the one that is playing in
+    /// brackets
+    fn buttons(&self, state: &AppState) -> Result<Vec<Button<Self>>> {
+        let mut buttons = vec![Button::back()];
+
+        for (idx, (path, tag)) in self.sprites.iter().enumerate() {
+            let name = match tag {
+                Some(tag) => tag.clone(),
+                None => path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            };
+
+            let label = match &state.matrix_animation {
+                MatrixAnimation::Sprite(current, current_tag)
+                    if current == path && current_tag == tag =>
+                {
+                    format!("[{name}]")
+                }
+                _ => name,
+            };
+
+            let image = ImageSourceType::Rgb(render_text(label, 16)?);
+
+            buttons.push(
+                Button::new(idx as u8 + 1, image).run(move |page: &mut Self, state| {
+                    let (path, tag) = &page.sprites[idx];
+
+                    state.matrix_animation = MatrixAnimation::Sprite(path.clone(), tag.clone());
+                    state.start_matrix_animation();
+
+                    Ok(())
+                }),
+            );
+        }
+
+        Ok(buttons)
+    }
+}
@@ -1,22 +1,27 @@
 use std::io::Cursor;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use image::{GenericImage, ImageFormat, Rgb, RgbImage, RgbaImage};
+use serde::Deserialize;
 use text_to_png::{FontSize, TextRenderer};
 
+use crate::emoji::EmojiPack;
+
 const FONT_DATA: &[u8] = include_bytes!("../assets/font.ttf");
 
-pub fn render_text<T: AsRef<str>, S: TryInto<FontSize>>(text: T, size: S) -> Result<RgbImage> {
-    let renderer = TextRenderer::try_new_with_ttf_font_data(FONT_DATA)?;
-    let text_png = renderer.render_text_to_png_data(text, size, "#ffffff")?;
+// Key faces are made for the 15 key StreamDeck, the deck scales them for other models
+const KEY_SIZE: u32 = 72;
 
-    let image = image::load(Cursor::new(&text_png.data), ImageFormat::Png)?;
+const LABEL_SIZE: i32 = 16;
+const LABEL_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+const OUTLINE_COLOR: Rgb<u8> = Rgb([0, 0, 0]);
 
-    let rgba = image.to_rgba8();
+pub fn render_text<T: AsRef<str>, S: TryInto<FontSize>>(text: T, size: S) -> Result<RgbImage> {
+    let rgba = render_glyphs(text, size)?;
 
     let mut target = RgbaImage::new(72, 72);
-    let x = (72 - text_png.size.width) / 2;
-    let y = (72 - text_png.size.height) / 2;
+    let x = (72 - rgba.width()) / 2;
+    let y = (72 - rgba.height()) / 2;
 
     target.copy_from(&rgba, x, y)?;
 
@@ -38,3 +43,271 @@ pub fn render_text<T: AsRef<str>, S: TryInto<FontSize>>(text: T, size: S) -> Res
 
     Ok(rgb_img)
 }
+
+/// White text on a transparent image that is just big enough for it
+fn render_glyphs<T: AsRef<str>, S: TryInto<FontSize>>(text: T, size: S) -> Result<RgbaImage> {
+    let renderer = TextRenderer::try_new_with_ttf_font_data(FONT_DATA)?;
+    let text_png = renderer.render_text_to_png_data(text, size, "#ffffff")?;
+
+    let image = image::load(Cursor::new(&text_png.data), ImageFormat::Png)?;
+
+    Ok(image.to_rgba8())
+}
+
+/// What is behind everything else on a key
+///
+/// In `menus.toml` a color is written like `"#ff8000"`, and a gradient as the colors at the top
+/// and the bottom of the key, like `["#ff8000", "#400080"]`.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(try_from = "BackgroundColors")]
+pub enum Background {
+    Color(Rgb<u8>),
+
+    /// From the top of the key to the bottom
+    Gradient(Rgb<u8>, Rgb<u8>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BackgroundColors {
+    Color(String),
+    Gradient(String, String),
+}
+
+impl TryFrom<BackgroundColors> for Background {
+    type Error = anyhow::Error;
+
+    fn try_from(colors: BackgroundColors) -> Result<Self> {
+        Ok(match colors {
+            BackgroundColors::Color(color) => Self::Color(parse_color(&color)?),
+            BackgroundColors::Gradient(top, bottom) => {
+                Self::Gradient(parse_color(&top)?, parse_color(&bottom)?)
+            }
+        })
+    }
+}
+
+impl Background {
+    fn at(&self, y: u32) -> Rgb<u8> {
+        match *self {
+            Self::Color(color) => color,
+            Self::Gradient(top, bottom) => {
+                let t = y as f32 / (KEY_SIZE - 1) as f32;
+
+                Rgb([0, 1, 2]
+                    .map(|c| (top[c] as f32 + (bottom[c] as f32 - top[c] as f32) * t) as u8))
+            }
+        }
+    }
+}
+
+/// Reads a color like `#ff8000`
+pub fn parse_color(color: &str) -> Result<Rgb<u8>> {
+    let hex = color
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6 && hex.bytes().all(|byte| byte.is_ascii_hexdigit()))
+        .ok_or(anyhow!("Color {color} is not like #ff8000"))?;
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+
+    Ok(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+}
+
+/// A key image that is put together at runtime, from the bottom up: a background, an emoji,
+/// an icon and a label
+///
+/// The emoji and the icon are in the middle of the key, or above the label when there is one.
+/// The label is white with a black outline, so it can be read on top of anything.
+pub struct KeyFace<'a> {
+    background: Background,
+    emoji: Option<&'a [u8]>,
+    icon: Option<RgbImage>,
+    label: Option<String>,
+}
+
+impl Default for KeyFace<'_> {
+    /// A black key
+    fn default() -> Self {
+        Self {
+            background: Background::Color(Rgb([0, 0, 0])),
+            emoji: None,
+            icon: None,
+            label: None,
+        }
+    }
+}
+
+impl<'a> KeyFace<'a> {
+    pub fn background(mut self, background: Background) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Emojis that are not in the pack are left out
+    pub fn emoji(mut self, emojis: &'a EmojiPack, name: &str) -> Self {
+        self.emoji = emojis.get_emoji(name);
+        self
+    }
+
+    /// Scaled down to fit when it is too big
+    pub fn icon(mut self, icon: RgbImage) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn render(self) -> Result<RgbImage> {
+        let mut face = RgbImage::from_fn(KEY_SIZE, KEY_SIZE, |_, y| self.background.at(y));
+
+        let picture = self.emoji.is_some() || self.icon.is_some();
+
+        // Room that is left above the label, for the emoji and the icon
+        let room = match (&self.label, picture) {
+            (Some(_), true) => KEY_SIZE - LABEL_SIZE as u32 - 8,
+            _ => KEY_SIZE,
+        };
+
+        if let Some(emoji) = self.emoji {
+            let emoji = RgbImage::from_raw(32, 32, emoji.to_vec())
+                .ok_or(anyhow!("Emoji has the wrong size"))?;
+
+            let y = room.saturating_sub(32) / 2;
+            image::imageops::overlay(&mut face, &emoji, 20, y as i64);
+        }
+
+        if let Some(icon) = self.icon {
+            let icon = fit(icon, KEY_SIZE, room);
+
+            let x = (KEY_SIZE - icon.width()) / 2;
+            let y = (room - icon.height()) / 2;
+            image::imageops::overlay(&mut face, &icon, x as i64, y as i64);
+        }
+
+        if let Some(label) = &self.label {
+            let glyphs = render_glyphs(label, LABEL_SIZE)?;
+            let glyphs = if glyphs.width() > KEY_SIZE {
+                let height = glyphs.height() * KEY_SIZE / glyphs.width();
+
+                image::imageops::resize(
+                    &glyphs,
+                    KEY_SIZE,
+                    height,
+                    image::imageops::FilterType::Gaussian,
+                )
+            } else {
+                glyphs
+            };
+
+            // Centered like `render_text` without a picture, at the bottom with one
+            let x = ((KEY_SIZE - glyphs.width()) / 2) as i64;
+            let y = match picture {
+                true => (KEY_SIZE - 4).saturating_sub(glyphs.height()) as i64,
+                false => (KEY_SIZE.saturating_sub(glyphs.height()) / 2) as i64,
+            };
+
+            for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+                blend(&mut face, &glyphs, x + dx, y + dy, OUTLINE_COLOR);
+            }
+
+            blend(&mut face, &glyphs, x, y, LABEL_COLOR);
+        }
+
+        Ok(face)
+    }
+}
+
+/// Scales `image` down to fit in `width` by `height`, keeping its shape
+fn fit(image: RgbImage, width: u32, height: u32) -> RgbImage {
+    if image.width() <= width && image.height() <= height {
+        return image;
+    }
+
+    let scale = f32::min(
+        width as f32 / image.width() as f32,
+        height as f32 / image.height() as f32,
+    );
+
+    crate::image::resize(
+        &image,
+        (image.width() as f32 * scale) as u32,
+        (image.height() as f32 * scale) as u32,
+        true,
+    )
+}
+
+/// Paints `color` on `face` through the alpha of `mask`, placed at `x`, `y`
+fn blend(face: &mut RgbImage, mask: &RgbaImage, x: i64, y: i64, color: Rgb<u8>) {
+    for (mx, my, pixel) in mask.enumerate_pixels() {
+        let (fx, fy) = (x + mx as i64, y + my as i64);
+
+        if fx < 0 || fy < 0 || fx >= face.width() as i64 || fy >= face.height() as i64 {
+            continue;
+        }
+
+        let alpha = pixel[3] as f32 / 255.0;
+        let under = face.get_pixel_mut(fx as u32, fy as u32);
+
+        for c in 0..3 {
+            under[c] = (under[c] as f32 * (1.0 - alpha) + color[c] as f32 * alpha) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_faces() {
+        let (red, blue) = (Rgb([255, 0, 0]), Rgb([0, 0, 255]));
+
+        let gradient = KeyFace::default()
+            .background(Background::Gradient(red, blue))
+            .render()
+            .unwrap();
+        assert_eq!(*gradient.get_pixel(0, 0), red);
+        assert_eq!(*gradient.get_pixel(71, 71), blue);
+
+        // Plain labels look like the labels of the built-in menus
+        let label = KeyFace::default().label("Plasma").render().unwrap();
+        assert_eq!(label, render_text("Plasma", 16).unwrap());
+
+        let outlined = KeyFace::default()
+            .background(Background::Color(red))
+            .label("Hi")
+            .render()
+            .unwrap();
+        assert!(outlined
+            .pixels()
+            .any(|pixel| *pixel == Rgb([255, 255, 255])));
+        assert!(outlined.pixels().any(|pixel| *pixel == Rgb([0, 0, 0])));
+
+        // An icon with a label is scaled down to make room for the label below it
+        let labeled = KeyFace::default()
+            .icon(RgbImage::from_pixel(72, 72, blue))
+            .label("Hi")
+            .render()
+            .unwrap();
+        assert_eq!(*labeled.get_pixel(36, 0), blue);
+        assert_eq!(*labeled.get_pixel(2, 2), Rgb([0, 0, 0]));
+        assert_ne!(*labeled.get_pixel(36, 60), blue);
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#c08000").unwrap(), Rgb([0xc0, 0x80, 0x00]));
+        assert_eq!(parse_color("#FFfFff").unwrap(), Rgb([255, 255, 255]));
+
+        assert!(parse_color("orange").is_err());
+        assert!(parse_color("#c080").is_err());
+
+        // Six bytes that are not all hex digits, with signs or a character of three bytes
+        assert!(parse_color("#+f+f+f").is_err());
+        assert!(parse_color("#€abc").is_err());
+        assert!(parse_color("#ab€c").is_err());
+    }
+}
@@ -1,36 +1,188 @@
+use std::{path::PathBuf, time::Duration};
+
+use image::Rgb;
+use serde::Deserialize;
+
 use crate::{
+    audio::AudioBuffer,
+    camera::CameraSource,
     deck::DeckReceiver,
     emoji::EmojiPack,
     matrix::{
         animations::{
-            Animation, BlocksAnimation, EyesAnimation, FallingAnimation, SequenceAnimation,
-            TimeAnimation,
+            Animation, AsepriteAnimation, BlocksAnimation, ClockFace, EyesAnimation, EyesControl,
+            FallingAnimation, FireAnimation, LifeAnimation, MaskAnimation, MouthAnimation,
+            OverlayAnimation, ParticleAnimation, ParticlePreset, PlasmaAnimation, RepeatAnimation,
+            ScriptAnimation, SequenceAnimation, SpectrumAnimation, SpectrumMode,
+            SplitScreenAnimation, StarfieldAnimation, StartupAnimation, TimeAnimation,
+            TimeLimitAnimation,
         },
+        color_utils::Palette,
         Matrix,
     },
 };
 
+/// The animations on the matrix, named in kebab-case in `menus.toml`
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum MatrixAnimation {
-    Time,
-    TimeNoClock,
+    Time(ClockFace),
     Eyes,
     Falling,
     Blocks,
+    Script(PathBuf),
+    Mix(Mix),
+    Particles(ParticlePreset),
+    Effect(Effect),
+    Spectrum(SpectrumMode),
+
+    /// The eyes, with a mouth that moves along with the audio
+    TalkingFace,
+
+    /// A tag of an Aseprite file, or all of its frames when there is no tag
+    Sprite(PathBuf, Option<String>),
 
     // Special animation that combines all other animations and switches between them
     Sequence,
 }
 
 impl MatrixAnimation {
-    fn animation(&self) -> Box<dyn Animation + Send + Sync> {
+    fn animation(&self, state: &AppState) -> Box<dyn Animation + Send + Sync> {
+        let palette = state.palette;
+
+        match self {
+            MatrixAnimation::Time(face) => Box::new(TimeAnimation::new(*face, palette)),
+            MatrixAnimation::Eyes => Box::new(EyesAnimation::with_control(state.eyes.clone())),
+            MatrixAnimation::Falling => Box::new(FallingAnimation::new(palette)),
+            MatrixAnimation::Blocks => Box::new(BlocksAnimation::new(palette)),
+            MatrixAnimation::Script(path) => Box::new(ScriptAnimation::new(path.clone(), palette)),
+            MatrixAnimation::Mix(mix) => mix.animation(palette),
+            MatrixAnimation::Particles(preset) => {
+                Box::new(ParticleAnimation::new(*preset, palette))
+            }
+            MatrixAnimation::Effect(effect) => effect.animation(palette, state.speed),
+            MatrixAnimation::Spectrum(mode) => {
+                Box::new(SpectrumAnimation::new(state.audio.clone(), *mode, palette))
+            }
+            MatrixAnimation::TalkingFace => Box::new(OverlayAnimation::new(
+                Box::new(EyesAnimation::with_control(state.eyes.clone())),
+                Box::new(MouthAnimation::new(state.audio.clone())),
+                Rgb([0, 0, 0]),
+            )),
+            MatrixAnimation::Sprite(path, tag) => {
+                match AsepriteAnimation::open(path, tag.as_deref()) {
+                    Ok(animation) => Box::new(animation),
+                    Err(why) => {
+                        log::warn!("Failed to load sprite {}: {why:#}", path.display());
+
+                        Box::new(SequenceAnimation::new(palette))
+                    }
+                }
+            }
+
+            MatrixAnimation::Sequence => Box::new(SequenceAnimation::new(palette)),
+        }
+    }
+}
+
+/// Procedural effects, which can run at different speeds
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Effect {
+    Plasma,
+    Fire,
+    Starfield,
+    Life,
+}
+
+impl Effect {
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Plasma => Self::Fire,
+            Self::Fire => Self::Starfield,
+            Self::Starfield => Self::Life,
+            Self::Life => Self::Plasma,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Plasma => "Plasma",
+            Self::Fire => "Fire",
+            Self::Starfield => "Stars",
+            Self::Life => "Life",
+        }
+    }
+
+    fn animation(&self, palette: Palette, speed: f32) -> Box<dyn Animation + Send + Sync> {
         match self {
-            MatrixAnimation::Time => Box::new(TimeAnimation::new(false)),
-            MatrixAnimation::TimeNoClock => Box::new(TimeAnimation::new(true)),
-            MatrixAnimation::Eyes => Box::new(EyesAnimation::new()),
-            MatrixAnimation::Falling => Box::new(FallingAnimation::new()),
-            MatrixAnimation::Blocks => Box::new(BlocksAnimation::new()),
+            Self::Plasma => Box::new(PlasmaAnimation::new(palette, speed)),
+            Self::Fire => Box::new(FireAnimation::new(palette, speed)),
+            Self::Starfield => Box::new(StarfieldAnimation::new(palette, speed)),
+            Self::Life => Box::new(LifeAnimation::new(palette, speed)),
+        }
+    }
+}
+
+/// Animations that are made by combining the other animations
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mix {
+    /// The clock on top of falling rain
+    RainClock,
+
+    /// Moving blocks that only show through the clock digits and border
+    BlockDigits,
+
+    /// Analog clock on the left, eyes on the right
+    EyesClock,
+
+    /// The startup animation, over and over again
+    Ferris,
+}
+
+impl Mix {
+    pub fn next(&self) -> Self {
+        match self {
+            Self::RainClock => Self::BlockDigits,
+            Self::BlockDigits => Self::EyesClock,
+            Self::EyesClock => Self::Ferris,
+            Self::Ferris => Self::RainClock,
+        }
+    }
 
-            MatrixAnimation::Sequence => Box::new(SequenceAnimation::new()),
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RainClock => "Rain",
+            Self::BlockDigits => "Digits",
+            Self::EyesClock => "Split",
+            Self::Ferris => "Ferris",
+        }
+    }
+
+    fn animation(&self, palette: Palette) -> Box<dyn Animation + Send + Sync> {
+        match self {
+            Self::RainClock => Box::new(OverlayAnimation::new(
+                Box::new(FallingAnimation::new(palette)),
+                Box::new(TimeAnimation::new(ClockFace::Digital, palette)),
+                Rgb([0, 0, 0]),
+            )),
+            Self::BlockDigits => Box::new(MaskAnimation::new(
+                Box::new(BlocksAnimation::new(palette)),
+                Box::new(TimeAnimation::new(ClockFace::Digital, palette)),
+            )),
+            Self::EyesClock => Box::new(SplitScreenAnimation::new(
+                Box::new(TimeAnimation::new(ClockFace::Analog, palette)),
+                Box::new(EyesAnimation::new()),
+            )),
+            Self::Ferris => Box::new(RepeatAnimation::new(u32::MAX, || {
+                let startup = StartupAnimation::load().expect("Ferris is embedded in the binary");
+
+                Box::new(TimeLimitAnimation::new(
+                    Box::new(startup),
+                    Duration::from_secs(3),
+                ))
+            })),
         }
     }
 }
@@ -41,12 +193,24 @@ pub struct AppState {
     pub emojis: EmojiPack,
 
     pub matrix_animation: MatrixAnimation,
+    pub palette: Palette,
+
+    /// Speed multiplier for the procedural effects
+    pub speed: f32,
+
+    /// Music for the visualizers, from the capture device or a WAV file
+    pub audio: AudioBuffer,
+
+    /// Moods and gaze of the eyes, steered from the deck
+    pub eyes: EyesControl,
+
+    pub camera: CameraSource,
 }
 
 impl AppState {
     pub fn start_matrix_animation(&self) {
         self.matrix
-            .set_animation(self.matrix_animation.animation())
+            .set_animation(self.matrix_animation.animation(self))
             .ok();
     }
 }